@@ -1,4 +1,8 @@
+pub mod merge;
 pub mod modal;
+pub mod notifications;
+pub mod sidebar;
+pub mod tabs;
 
 use iced::{
     Element,
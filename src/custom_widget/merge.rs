@@ -0,0 +1,135 @@
+use iced::{
+    Alignment, Element, Font, Length,
+    widget::{button, column, container, horizontal_space, row, scrollable, text},
+};
+
+use crate::{
+    message::Message,
+    montagne_merge::{Hunk, HunkKind, Merge, Side},
+    montagne_theme::hunk_style,
+};
+
+/// Unchanged stretches are folded down to this many lines of context on each end.
+const FOLD_CONTEXT: usize = 3;
+
+/// Side by side view of a three-way merge (base, version on disk and our buffer)
+/// letting the user pick a side for every hunk that changed.
+pub fn merge_view<'a>(merge: &'a Merge) -> Element<'a, Message> {
+    let titles = row![
+        text("Base (last loaded)").width(Length::Fill),
+        text("On disk").width(Length::Fill),
+        text("Yours").width(Length::Fill),
+    ]
+    .spacing(10);
+
+    let hunks = merge
+        .hunks()
+        .iter()
+        .enumerate()
+        .fold(column![].spacing(4), |hunks, (index, hunk)| {
+            hunks.push(hunk_view(index, hunk))
+        });
+
+    let summary = match merge.conflict_count() {
+        0 => "No conflicts.".to_string(),
+        conflicts => format!(
+            "{} conflicting hunks, your version is kept unless you pick another.",
+            conflicts
+        ),
+    };
+
+    let footer = row![
+        text(summary),
+        horizontal_space(),
+        button("Apply merge").on_press(Message::ApplyMerge),
+        button("Cancel")
+            .on_press(Message::CancelMerge)
+            .style(button::secondary),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+
+    column![titles, scrollable(hunks).height(Length::Fill), footer]
+        .spacing(10)
+        .padding(5)
+        .into()
+}
+
+fn hunk_view(index: usize, hunk: &Hunk) -> Element<'_, Message> {
+    if hunk.kind == HunkKind::Unchanged {
+        let folded = fold(&hunk.base);
+
+        return row![
+            version_text(folded.clone()),
+            version_text(folded.clone()),
+            version_text(folded),
+        ]
+        .spacing(10)
+        .into();
+    }
+
+    let version = |version: &str| {
+        container(version_text(
+            version.strip_suffix('\n').unwrap_or(version).to_string(),
+        ))
+        .width(Length::Fill)
+        .padding(4)
+        .style(hunk_style(hunk.kind))
+    };
+
+    let versions = row![
+        version(&hunk.base),
+        version(&hunk.disk),
+        version(&hunk.mine)
+    ]
+    .spacing(10);
+
+    if !hunk.is_choice() {
+        return versions.into();
+    }
+
+    let pick = |label, side| {
+        let pick = button(text(label).size(14)).on_press(Message::ResolveHunk(index, side));
+
+        if hunk.side == side {
+            pick.style(button::primary)
+        } else {
+            pick.style(button::secondary)
+        }
+    };
+
+    column![
+        versions,
+        row![
+            horizontal_space(),
+            pick("Use disk version", Side::Disk),
+            pick("Use my version", Side::Mine),
+        ]
+        .spacing(10)
+    ]
+    .spacing(4)
+    .into()
+}
+
+fn version_text<'a>(version: String) -> Element<'a, Message> {
+    text(version)
+        .font(Font::MONOSPACE)
+        .width(Length::Fill)
+        .into()
+}
+
+/// Collapse the middle of a long unchanged stretch.
+fn fold(unchanged: &str) -> String {
+    let lines: Vec<&str> = unchanged.lines().collect();
+
+    if lines.len() <= FOLD_CONTEXT * 2 + 1 {
+        return lines.join("\n");
+    }
+
+    format!(
+        "{}\n  ⋯ {} unchanged lines ⋯\n{}",
+        lines[..FOLD_CONTEXT].join("\n"),
+        lines.len() - FOLD_CONTEXT * 2,
+        lines[lines.len() - FOLD_CONTEXT..].join("\n"),
+    )
+}
@@ -1,12 +1,28 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use iced::{
-    Border, Color, Element,
+    Alignment, Border, Color, Element, Font, Length,
     border::Radius,
-    widget::{button, center, column, container, opaque, row, stack, text, tooltip},
+    widget::{
+        button, center, column, container, opaque, row, scrollable, stack, text, text_input,
+        tooltip,
+    },
 };
 
-use crate::{message::Message, montagne_theme::modal_style};
+use crate::{
+    custom_widget::notifications::log_entry,
+    message::Message,
+    montagne_assets::is_image,
+    montagne_backup::Backup,
+    montagne_encoding::TextEncoding,
+    montagne_file_io::{Error, LineEnding, OpenWarning},
+    montagne_journal::Recovery,
+    montagne_notifications::Notifications,
+    montagne_recent::RecentFile,
+    montagne_theme::{diff_line_color, modal_style},
+    montagne_workspace::Prompt,
+};
 
 // This has the where clause to confirm Message is what I want it to be
 /// Generic implementation for stacking `content` on top of `base`.
@@ -37,14 +53,24 @@ where
 }
 
 // This needs to use message::Message for specific behavior
-/// Stack file exit dialogue on top of base content.
-pub fn exit_modal<'a>(base: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+/// Stack file exit dialogue on top of base content, listing the `dirty` documents by name.
+pub fn exit_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    dirty: Vec<String>,
+) -> Element<'a, Message> {
+    let documents = column(
+        dirty
+            .into_iter()
+            .map(|name| text(format!("• {}", name)).into()),
+    );
+
     modal(
         base,
         column![
             text("You have unsaved work. Save changes?"),
+            documents,
             row![
-                button("Save").on_press(Message::SaveFile),
+                button("Save all").on_press(Message::SaveAll),
                 button("Close without saving").on_press(Message::CloseApp),
                 button("Go back").on_press(Message::CloseExitModal),
             ]
@@ -55,6 +81,194 @@ pub fn exit_modal<'a>(base: impl Into<Element<'a, Message>>) -> Element<'a, Mess
     )
 }
 
+/// The tab being closed has unsaved changes.
+pub fn close_tab_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    name: String,
+) -> Element<'a, Message> {
+    modal(
+        base,
+        column![
+            text(format!("Save changes to {} before closing?", name)),
+            row![
+                button("Save").on_press(Message::SaveAndCloseTab),
+                button("Close without saving").on_press(Message::CloseFile),
+                button("Go back").on_press(Message::CancelCloseTab),
+            ]
+            .spacing(10)
+        ]
+        .spacing(10)
+        .padding(30),
+    )
+}
+
+/// The name input of the rename dialog, focused as it opens.
+pub fn rename_input_id() -> text_input::Id {
+    text_input::Id::new("rename-input")
+}
+
+/// Give the active file a new `name`, in the same folder.
+pub fn rename_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    name: &'a str,
+) -> Element<'a, Message> {
+    let can_rename = !name.trim().is_empty();
+
+    modal(
+        base,
+        column![
+            text("Rename to"),
+            text_input("New name", name)
+                .id(rename_input_id())
+                .on_input(Message::RenameInputChanged)
+                .on_submit_maybe(can_rename.then_some(Message::ConfirmRename)),
+            row![
+                button("Rename").on_press_maybe(can_rename.then_some(Message::ConfirmRename)),
+                button("Go back").on_press(Message::CancelRename),
+            ]
+            .spacing(10)
+        ]
+        .spacing(10)
+        .padding(30)
+        .width(500),
+    )
+}
+
+/// The name input of the workspace dialog, focused as it opens.
+pub fn workspace_input_id() -> text_input::Id {
+    text_input::Id::new("workspace-input")
+}
+
+/// Ask for the name of a new or renamed workspace entry, or confirm deleting one.
+pub fn workspace_prompt_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    prompt: &'a Prompt,
+    name: &'a str,
+) -> Element<'a, Message> {
+    let (title, action) = match prompt {
+        Prompt::NewFile(folder) => (format!("New file in {}", folder.display()), "Create"),
+        Prompt::NewFolder(folder) => (format!("New folder in {}", folder.display()), "Create"),
+        Prompt::Rename(path) => (format!("Rename {}", path.display()), "Rename"),
+        Prompt::Delete(path) => (
+            format!(
+                "Delete {}? This can't be undone, folders go with everything in them.",
+                path.display()
+            ),
+            "Delete",
+        ),
+    };
+
+    let confirm = (matches!(prompt, Prompt::Delete(_)) || !name.trim().is_empty())
+        .then_some(Message::ConfirmWorkspacePrompt);
+
+    let input = (!matches!(prompt, Prompt::Delete(_))).then(|| {
+        text_input("Name", name)
+            .id(workspace_input_id())
+            .on_input(Message::WorkspaceNameChanged)
+            .on_submit_maybe(confirm.clone())
+    });
+
+    modal(
+        base,
+        column![text(title)]
+            .push_maybe(input)
+            .push(
+                row![
+                    button(action).on_press_maybe(confirm),
+                    button("Go back").on_press(Message::CancelWorkspacePrompt),
+                ]
+                .spacing(10),
+            )
+            .spacing(10)
+            .padding(30)
+            .width(500),
+    )
+}
+
+/// A file operation failed, offer to try it again, or to save elsewhere when it was a save.
+pub fn file_error_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    error: &Error,
+    is_save: bool,
+) -> Element<'a, Message> {
+    let save_as = is_save.then(|| button("Save as").on_press(Message::SaveFailedAs));
+
+    modal(
+        base,
+        column![
+            text(format!("{}.", error)),
+            row![button("Try again").on_press(Message::RetryFailed)]
+                .push_maybe(save_as)
+                .push(button("Dismiss").on_press(Message::DismissFailure))
+                .spacing(10),
+        ]
+        .spacing(10)
+        .padding(30)
+        .width(600),
+    )
+}
+
+/// The file at `path` is large or binary, ask before opening it. `count` files are waiting to be
+/// asked about, this one included.
+pub fn open_warning_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    path: &Path,
+    warning: OpenWarning,
+    count: usize,
+) -> Element<'a, Message> {
+    let consequence = match warning {
+        OpenWarning::Large(_) => {
+            "Opening it may take a while, it is edited as plain text without preview or highlighting."
+        }
+        OpenWarning::Binary => "It may show as garbage, and saving it could damage it.",
+    };
+
+    let remaining = (count > 1).then(|| text(format!("{} more after this one", count - 1)));
+
+    modal(
+        base,
+        column![
+            text(format!("{} {}.", path.display(), warning)),
+            text(consequence),
+            row![
+                button("Open anyway").on_press(Message::OpenAnyway),
+                button("Don't open").on_press(Message::SkipOpen),
+            ]
+            .push_maybe(remaining)
+            .spacing(10)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(10)
+        .padding(30)
+        .width(600),
+    )
+}
+
+/// Shown while files are dragged over the window, telling what dropping them does.
+pub fn drop_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    hovered: &'a [PathBuf],
+) -> Element<'a, Message> {
+    let files = column(hovered.iter().map(|path| {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let verb = if is_image(path) { "Link" } else { "Open" };
+
+        text(format!("{} {}", verb, name)).into()
+    }))
+    .spacing(5);
+
+    modal(
+        base,
+        column![text("Drop files here").size(20), files]
+            .spacing(10)
+            .padding(30),
+    )
+}
+
 /// In case file is dirty (is_dirty == true) while base content of file has changed, show user
 /// this modal to ask them what to do.
 pub fn file_changed_modal<'a>(
@@ -77,6 +291,390 @@ pub fn file_changed_modal<'a>(
             )
             .style(container::rounded_box),
             button("Reload file").on_press(Message::LoadFile),
+            tooltip(
+                button("Compare and merge").on_press(Message::OpenMerge),
+                "Review both versions side by side and pick per change.",
+                tooltip::Position::Top
+            )
+            .style(container::rounded_box),
         ],
     )
 }
+
+/// The active file was deleted, or moved to `renamed_to`, while we still have it open.
+/// Offer to put it back, save it elsewhere, follow the move or close it.
+pub fn file_missing_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    file_path: impl Into<PathBuf>,
+    renamed_to: Option<PathBuf>,
+) -> Element<'a, Message> {
+    let path = file_path.into();
+
+    let (message, follow_rename) = match renamed_to {
+        Some(destination) => (
+            format!("{} was moved to {}.", path.display(), destination.display()),
+            Some(button("Follow rename").on_press(Message::FollowRename(destination))),
+        ),
+        None => (format!("{} was deleted.", path.display()), None),
+    };
+
+    modal(
+        base,
+        column![
+            text(message),
+            row![
+                button("Save to original path").on_press(Message::SaveFile),
+                button("Save as").on_press(Message::SaveFileAs),
+            ]
+            .push_maybe(follow_rename)
+            // the buffer is all that is left of the file, closing asks first
+            .push(button("Close").on_press(Message::CloseActiveTab))
+            .spacing(10)
+        ]
+        .spacing(10)
+        .padding(30),
+    )
+}
+
+/// We lost write permission to the active file while it has unsaved changes.
+pub fn file_read_only_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    file_path: impl Into<PathBuf>,
+) -> Element<'a, Message> {
+    let path = file_path.into();
+
+    modal(
+        base,
+        column![
+            text(format!(
+                "{} is now read-only. Your changes can't be saved there.",
+                path.display()
+            )),
+            row![
+                button("Save as").on_press(Message::SaveFileAs),
+                button("Keep editing").on_press(Message::DismissDiskChange),
+                // there are unsaved changes, closing asks first
+                button("Close").on_press(Message::CloseActiveTab),
+            ]
+            .spacing(10)
+        ]
+        .spacing(10)
+        .padding(30),
+    )
+}
+
+/// Browse the backups of the active file, newest first, and restore one into the buffer.
+pub fn backups_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    backups: &'a [Backup],
+) -> Element<'a, Message> {
+    let list: Element<'a, Message> = if backups.is_empty() {
+        text("No backups of this file yet. Set `mode` under `[backup]` in .montagne.toml to keep them.")
+            .into()
+    } else {
+        let rows = backups.iter().fold(column![].spacing(5), |rows, backup| {
+            rows.push(
+                row![
+                    text(age(backup.taken)).width(Length::Fill),
+                    text(format!("{} bytes", backup.len)),
+                    tooltip(
+                        button("Restore").on_press(Message::RestoreBackup(backup.path.clone())),
+                        text(backup.path.display().to_string()),
+                        tooltip::Position::Left
+                    )
+                    .style(container::rounded_box),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+        });
+
+        container(scrollable(rows)).max_height(300).into()
+    };
+
+    modal(
+        base,
+        column![
+            text("Backups"),
+            list,
+            button("Close").on_press(Message::CloseBackups),
+        ]
+        .spacing(10)
+        .padding(30)
+        .width(500),
+    )
+}
+
+/// Everything that was notified lately, newest first.
+pub fn notifications_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    notifications: &'a Notifications,
+) -> Element<'a, Message> {
+    let entries = notifications
+        .history()
+        .fold(column![].spacing(8), |entries, notification| {
+            entries.push(log_entry(notification))
+        });
+
+    modal(
+        base,
+        column![
+            text("Notifications"),
+            container(scrollable(entries)).max_height(400),
+            row![
+                button("Acknowledge all").on_press(Message::AcknowledgeAllNotifications),
+                button("Clear").on_press(Message::ClearNotifications),
+                button("Close").on_press(Message::CloseNotifications),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10)
+        .padding(30)
+        .width(700),
+    )
+}
+
+/// The filter of the recent files picker, focused as it opens.
+pub fn recent_filter_id() -> text_input::Id {
+    text_input::Id::new("recent-filter")
+}
+
+/// Pick a recently opened file, by clicking it or by typing part of its path and pressing Enter.
+/// `selected` is the index in `files` Enter opens.
+pub fn recent_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    files: Vec<&'a RecentFile>,
+    filter: &'a str,
+    selected: usize,
+) -> Element<'a, Message> {
+    let filter_input = text_input("Type to filter, Enter to open", filter)
+        .id(recent_filter_id())
+        .on_input(Message::RecentFilterChanged)
+        .on_submit(Message::OpenSelectedRecent);
+
+    let list: Element<'a, Message> = if files.is_empty() {
+        text("No recent files").into()
+    } else {
+        let rows =
+            files
+                .into_iter()
+                .enumerate()
+                .fold(column![].spacing(5), |rows, (index, file)| {
+                    let name = file
+                        .path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let folder = file
+                        .path
+                        .parent()
+                        .map(|folder| folder.display().to_string())
+                        .unwrap_or_default();
+
+                    let open = button(column![text(name), text(folder).size(12)])
+                        .width(Length::Fill)
+                        .style(if index == selected {
+                            button::primary
+                        } else {
+                            button::text
+                        })
+                        .on_press(Message::OpenRecent(file.path.clone()));
+
+                    let pin = button(if file.pinned { "Unpin" } else { "Pin" })
+                        .style(button::secondary)
+                        .on_press(Message::TogglePinRecent(file.path.clone()));
+
+                    let remove = button("×")
+                        .style(button::secondary)
+                        .on_press(Message::RemoveRecent(file.path.clone()));
+
+                    rows.push(
+                        row![open, pin, remove]
+                            .spacing(10)
+                            .align_y(Alignment::Center),
+                    )
+                });
+
+        container(scrollable(rows)).max_height(400).into()
+    };
+
+    modal(
+        base,
+        column![
+            text("Recent files"),
+            filter_input,
+            list,
+            row![
+                button("Remove missing").on_press(Message::RemoveMissingRecent),
+                button("Close").on_press(Message::CloseRecent),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10)
+        .padding(30)
+        .width(600),
+    )
+}
+
+/// Pick an encoding to read the active file again with, or to save it with.
+/// Reopening is only offered when it wouldn't throw away unsaved changes.
+pub fn encodings_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    current: TextEncoding,
+    can_reopen: bool,
+) -> Element<'a, Message> {
+    let rows = TextEncoding::choices()
+        .into_iter()
+        .fold(column![].spacing(5), |rows, encoding| {
+            // reopening looks for a BOM either way, it only matters when saving
+            let reopen = button("Reopen")
+                .on_press_maybe(can_reopen.then_some(Message::ReopenWithEncoding(encoding)));
+
+            let label = if encoding == current {
+                format!("{} (current)", encoding)
+            } else {
+                encoding.to_string()
+            };
+
+            rows.push(
+                row![
+                    text(label).width(Length::Fill),
+                    reopen,
+                    button("Save").on_press(Message::SaveWithEncoding(encoding)),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+        });
+
+    modal(
+        base,
+        column![
+            text("Encoding"),
+            container(scrollable(rows)).max_height(400),
+            button("Close").on_press(Message::CloseEncodings),
+        ]
+        .spacing(10)
+        .padding(30)
+        .width(500),
+    )
+}
+
+/// Convert the line endings of the active file, which may currently be `mixed`.
+pub fn line_endings_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    current: LineEnding,
+    mixed: bool,
+) -> Element<'a, Message> {
+    let title = if mixed {
+        text(format!(
+            "The file mixes line endings, most lines use {}. Convert all of them to:",
+            current
+        ))
+    } else {
+        text(format!("Lines end with {}. Convert them to:", current))
+    };
+
+    let choices = LineEnding::ALL
+        .into_iter()
+        .fold(row![].spacing(10), |choices, line_ending| {
+            let label = match line_ending {
+                LineEnding::Lf => "LF (Unix, macOS)",
+                LineEnding::CrLf => "CRLF (Windows)",
+                LineEnding::Cr => "CR (classic Mac OS)",
+            };
+
+            choices.push(button(label).on_press_maybe(
+                (mixed || line_ending != current).then_some(Message::SetLineEnding(line_ending)),
+            ))
+        });
+
+    modal(
+        base,
+        column![
+            title,
+            choices,
+            button("Close").on_press(Message::CloseLineEndings),
+        ]
+        .spacing(10)
+        .padding(30),
+    )
+}
+
+/// Offer unsaved work left behind by a previous run that didn't exit cleanly,
+/// showing how it differs from the file on disk.
+pub fn recovery_modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    recovery: &'a Recovery,
+    count: usize,
+) -> Element<'a, Message> {
+    let name = match &recovery.entry.path {
+        Some(path) => path.display().to_string(),
+        None => "an unsaved new file".to_string(),
+    };
+
+    let taken = age(Some(
+        UNIX_EPOCH + Duration::from_secs(recovery.entry.saved_at),
+    ));
+
+    let title = text(format!(
+        "Montagne didn't close properly. Recover changes to {} ({})?",
+        name,
+        taken.to_lowercase()
+    ));
+
+    let details: Element<'a, Message> = match (&recovery.diff, &recovery.entry.path) {
+        (Some(diff), _) => {
+            let lines = diff.lines().fold(column![], |lines, line| {
+                lines.push(
+                    text(line)
+                        .font(Font::MONOSPACE)
+                        .size(13)
+                        .color(diff_line_color(line)),
+                )
+            });
+
+            container(scrollable(lines)).max_height(300).into()
+        }
+        (None, Some(_)) => text("The file no longer exists on disk.").into(),
+        (None, None) => container(scrollable(
+            text(&recovery.entry.text).font(Font::MONOSPACE).size(13),
+        ))
+        .max_height(300)
+        .into(),
+    };
+
+    let remaining = (count > 1).then(|| text(format!("{} more after this one", count - 1)));
+
+    modal(
+        base,
+        column![
+            title,
+            details,
+            row![
+                button("Recover").on_press(Message::RecoverJournal),
+                button("Discard").on_press(Message::DiscardRecovery),
+            ]
+            .push_maybe(remaining)
+            .spacing(10)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(10)
+        .padding(30)
+        .width(700),
+    )
+}
+
+/// How long ago something happened, roughly.
+pub fn age(taken: Option<SystemTime>) -> String {
+    let Some(elapsed) = taken.and_then(|taken| taken.elapsed().ok()) else {
+        return "Unknown time".to_string();
+    };
+
+    match elapsed.as_secs() {
+        0..60 => "Just now".to_string(),
+        seconds @ 60..3600 => format!("{} minutes ago", seconds / 60),
+        seconds @ 3600..86400 => format!("{} hours ago", seconds / 3600),
+        seconds => format!("{} days ago", seconds / 86400),
+    }
+}
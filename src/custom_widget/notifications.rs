@@ -0,0 +1,96 @@
+use iced::{
+    Alignment, Element, Length,
+    widget::{Column, button, container, row, text},
+};
+
+use crate::{
+    custom_widget::modal::age,
+    message::Message,
+    montagne_notifications::{Notification, Notifications, Severity},
+};
+
+/// The latest notification for the status bar, and how many errors wait to be acknowledged.
+/// Clicking it shows the log.
+pub fn summary(notifications: &Notifications) -> Element<'_, Message> {
+    let latest = notifications
+        .latest()
+        .map_or("Welcome to Montagne.", |latest| latest.message.as_str());
+
+    let errors = match notifications.pending_errors() {
+        0 => None,
+        1 => Some(text("1 error").style(text::danger)),
+        count => Some(text(format!("{} errors", count)).style(text::danger)),
+    };
+
+    button(row![text(latest)].push_maybe(errors).spacing(10))
+        .style(button::text)
+        .padding(0)
+        .on_press(Message::ShowNotifications)
+        .into()
+}
+
+/// The newest notifications, stacked in the bottom right corner over `base`.
+pub fn toasts<'a>(
+    base: impl Into<Element<'a, Message>>,
+    notifications: Vec<&'a Notification>,
+) -> Element<'a, Message> {
+    if notifications.is_empty() {
+        return base.into();
+    }
+
+    let toasts =
+        notifications
+            .into_iter()
+            .fold(Column::new().spacing(5), |toasts, notification| {
+                toasts.push(
+                    container(
+                        row![
+                            severity(notification.severity),
+                            text(&notification.message).width(Length::Fill),
+                            button(text("×").size(14))
+                                .style(button::text)
+                                .on_press(Message::AcknowledgeNotification(notification.id)),
+                        ]
+                        .spacing(10)
+                        .align_y(Alignment::Center),
+                    )
+                    .padding(10)
+                    .width(360)
+                    .style(container::rounded_box),
+                )
+            });
+
+    iced::widget::stack![
+        base.into(),
+        container(toasts)
+            .padding(20)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_right(Length::Fill)
+            .align_bottom(Length::Fill),
+    ]
+    .into()
+}
+
+/// One line of the log, the oldest at the bottom.
+pub fn log_entry(notification: &Notification) -> Element<'_, Message> {
+    row![
+        severity(notification.severity),
+        text(&notification.message).width(Length::Fill),
+        text(age(Some(notification.time))).size(12),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+fn severity<'a>(severity: Severity) -> Element<'a, Message> {
+    let label = text(severity.to_string()).size(12).width(60);
+
+    match severity {
+        Severity::Info => label.style(text::secondary),
+        Severity::Warning => label.style(text::primary),
+        Severity::Error => label.style(text::danger),
+    }
+    .into()
+}
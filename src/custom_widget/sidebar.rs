@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use iced::{
+    Alignment, Element, Length,
+    widget::{Column, button, column, horizontal_space, row, scrollable, text},
+};
+
+use crate::{
+    message::Message,
+    montagne_workspace::{Node, Workspace},
+};
+
+/// The workspace folder as a tree, folders expanded or collapsed by clicking them.
+/// The actions apply to the selected entry, `open` is the path of the active document.
+pub fn file_tree<'a>(workspace: &'a Workspace, open: Option<&Path>) -> Element<'a, Message> {
+    let has_selection = workspace.selected.is_some();
+
+    let title = row![
+        text(workspace.name()).size(16),
+        horizontal_space(),
+        button(text("×").size(14))
+            .style(button::text)
+            .on_press(Message::CloseFolder),
+    ]
+    .align_y(Alignment::Center);
+
+    let actions = row![
+        button(text("+ File").size(12)).on_press(Message::WorkspaceNewFile),
+        button(text("+ Folder").size(12)).on_press(Message::WorkspaceNewFolder),
+    ]
+    .spacing(5);
+
+    let selection_actions = row![
+        button(text("Rename").size(12))
+            .on_press_maybe(has_selection.then_some(Message::WorkspaceRename)),
+        button(text("Move").size(12))
+            .on_press_maybe(has_selection.then_some(Message::WorkspaceMove)),
+        button(text("Delete").size(12))
+            .style(button::danger)
+            .on_press_maybe(has_selection.then_some(Message::WorkspaceDelete)),
+    ]
+    .spacing(5);
+
+    let mut tree = Column::new().spacing(2);
+    for node in &workspace.nodes {
+        tree = push_node(tree, workspace, node, open, 0);
+    }
+
+    column![
+        title,
+        actions,
+        selection_actions,
+        scrollable(tree).height(Length::Fill)
+    ]
+    .spacing(8)
+    .width(260)
+    .into()
+}
+
+/// Add the row of `node`, and those of its contents when it is an expanded folder.
+fn push_node<'a>(
+    mut tree: Column<'a, Message>,
+    workspace: &'a Workspace,
+    node: &'a Node,
+    open: Option<&Path>,
+    depth: u16,
+) -> Column<'a, Message> {
+    let is_expanded = workspace.expanded.contains(&node.path);
+    let is_selected = workspace.selected.as_ref() == Some(&node.path);
+
+    let label = match &node.children {
+        Some(_) if is_expanded => format!("▾ {}", node.name),
+        Some(_) => format!("▸ {}", node.name),
+        None if open == Some(node.path.as_path()) => format!("• {}", node.name),
+        None => node.name.clone(),
+    };
+
+    tree = tree.push(
+        row![
+            horizontal_space().width(12 * depth),
+            button(text(label).size(14))
+                .width(Length::Fill)
+                .style(if is_selected {
+                    button::primary
+                } else {
+                    button::text
+                })
+                .on_press(Message::WorkspaceEntryClicked(node.path.clone())),
+        ]
+        .align_y(Alignment::Center),
+    );
+
+    if let Some(children) = &node.children
+        && is_expanded
+    {
+        for child in children {
+            tree = push_node(tree, workspace, child, open, depth + 1);
+        }
+    }
+
+    tree
+}
@@ -0,0 +1,73 @@
+use iced::{
+    Alignment, Element,
+    widget::{button, row, scrollable, text},
+};
+
+use crate::{message::Message, montagne_document::Document};
+
+/// One tab per open document, the `active` one highlighted.
+/// The active tab can be moved left or right, every tab can be closed.
+pub fn tab_bar<'a>(documents: &'a [Document], active: usize) -> Element<'a, Message> {
+    let tabs = documents.iter().enumerate().map(|(index, document)| {
+        let is_active = index == active;
+
+        let mut label = document.name();
+        if document.is_dirty {
+            label.push_str(" •");
+        }
+        if document.disk_change.is_some() {
+            // something happened on disk, the dialog shows once the tab is selected
+            label.push_str(" !");
+        }
+
+        let mut tab = row![].spacing(2).align_y(Alignment::Center);
+
+        if is_active && index > 0 {
+            tab = tab.push(
+                button(text("‹").size(12))
+                    .style(button::text)
+                    .on_press(Message::MoveTab(-1)),
+            );
+        }
+
+        tab = tab.push(
+            button(text(label).size(14))
+                .style(if is_active {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .on_press(Message::SelectTab(document.id)),
+        );
+
+        if is_active && index + 1 < documents.len() {
+            tab = tab.push(
+                button(text("›").size(12))
+                    .style(button::text)
+                    .on_press(Message::MoveTab(1)),
+            );
+        }
+
+        tab.push(
+            button(text("×").size(14))
+                .style(button::text)
+                .on_press(Message::CloseTab(document.id)),
+        )
+        .into()
+    });
+
+    let tabs = row(tabs)
+        .push(
+            button(text("+").size(14))
+                .style(button::text)
+                .on_press(Message::NewFile),
+        )
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+    scrollable(tabs)
+        .direction(scrollable::Direction::Horizontal(
+            scrollable::Scrollbar::new().width(2).scroller_width(2),
+        ))
+        .into()
+}
@@ -1,43 +1,163 @@
 // #![windows_subsystem = "windows"]
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use iced::widget::text_editor::{Action, Edit, Motion};
 use iced::{
-    Alignment, Element, Length, Padding, Subscription, Task, Theme, highlighter,
+    Alignment, Element, Length, Padding, Point, Size, Subscription, Task, Theme, highlighter,
+    keyboard,
     widget::{
-        self, button, center, column, container, horizontal_space, markdown, row, scrollable, text,
-        text_editor, toggler,
+        self, button, center, column, container, horizontal_space, markdown, progress_bar, row,
+        scrollable, text, text_editor, text_input, toggler,
     },
     window,
 };
+use serde::{Deserialize, Serialize};
 
 mod message;
 use message::Message;
 
 mod custom_widget;
-use custom_widget::{action, modal::{exit_modal, file_changed_modal}};
+use custom_widget::{
+    action,
+    merge::merge_view,
+    modal::{
+        backups_modal, close_tab_modal, drop_modal, encodings_modal, exit_modal,
+        file_changed_modal, file_error_modal, file_missing_modal, file_read_only_modal,
+        line_endings_modal, notifications_modal, open_warning_modal, recent_filter_id,
+        recent_modal, recovery_modal, rename_input_id, rename_modal, workspace_input_id,
+        workspace_prompt_modal,
+    },
+    notifications::{summary, toasts},
+    sidebar::file_tree,
+    tabs::tab_bar,
+};
 
 mod montagne_theme;
 use montagne_theme::{editor_style, new_icon, open_icon, preview_scrollable_style, save_icon};
 
+mod montagne_encoding;
+use montagne_encoding::TextEncoding;
+
+mod montagne_file_types;
+
 mod montagne_file_io;
-use montagne_file_io::{Error, load_file, open_file, save_file};
+use montagne_file_io::{
+    Error, FileStamp, LoadedFile, Loading, OpenWarning, load_file, load_with_progress, move_file,
+    open_checked, open_file, reopen_file, save_file,
+};
+
+mod montagne_assets;
+use montagne_assets::{copy_into_assets, image_link, is_image};
+
+mod montagne_backup;
+use montagne_backup::{Backup, list_backups};
+
+mod montagne_cli;
+use montagne_cli::{Args, FileArg, USAGE};
+
+mod montagne_config;
+use montagne_config::Config;
+
+mod montagne_instance;
+use montagne_instance::{Instance, Waiter};
+
+mod montagne_journal;
+use montagne_journal::{
+    JOURNAL_INTERVAL, JournalEntry, Recovery, discard_journal, forget_journal, recover,
+    write_journal,
+};
+
+mod montagne_document;
+use montagne_document::{Document, DocumentId};
+
+mod montagne_merge;
+use montagne_merge::Merge;
+
+mod montagne_recent;
+use montagne_recent::{RecentFiles, find_missing};
+
+mod montagne_session;
+use montagne_session::{DocumentSession, Session, WindowGeometry};
+
+mod montagne_shortcuts;
+use montagne_shortcuts::{editor_key_binding, picker_key, shortcut};
+
+mod montagne_watcher;
+use montagne_watcher::{Change, watch_folder};
+
+mod montagne_workspace;
+use montagne_workspace::{
+    Prompt, TreeChange, Workspace, create_file, create_folder, delete_entry, is_valid_name,
+    move_entry, moved_path, pick_destination, pick_folder, read_tree,
+};
+
+mod montagne_undo;
+
+mod montagne_title;
+use montagne_title::TitleParts;
+
+mod montagne_notifications;
+use montagne_notifications::{Notifications, Severity, TOAST_DURATION};
 
 fn main() -> iced::Result {
-    iced::application("Montagne", Montagne::update, Montagne::view)
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("montagne: {}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    // someone else is already running, let them open the files
+    if !args.standalone {
+        match montagne_instance::forward(&args) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(error) => eprintln!("montagne: {}, opening a window of our own", error),
+        }
+    }
+
+    // a `--wait` run exits once its files are closed, it mustn't take later invocations with it
+    let instance = if args.standalone || args.wait {
+        None
+    } else {
+        Instance::bind()
+    };
+
+    let session = Session::load();
+    let recent = RecentFiles::load();
+
+    let mut application = iced::application(Montagne::title, Montagne::update, Montagne::view)
         .subscription(Montagne::subscription)
         .exit_on_close_request(false)
         .centered()
         // .default_font(Font::MONOSPACE)
         .font(include_bytes!("../fonts/icons.ttf").as_slice())
-        .theme(Montagne::theme)
-        .run_with(Montagne::new)
+        .theme(Montagne::theme);
+
+    if let Some(geometry) = session.window {
+        application = application
+            .window_size(geometry.size())
+            .position(geometry.position());
+    }
+
+    application.run_with(move || Montagne::new(session, recent, args, instance))
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum Mode {
     Write,
     Preview,
     Split,
+    /// Files that aren't markdown: the editor alone, without a preview.
+    PlainText,
 }
 
 impl std::fmt::Display for Mode {
@@ -46,265 +166,1721 @@ impl std::fmt::Display for Mode {
             Mode::Write => write!(f, "Write"),
             Mode::Preview => write!(f, "Preview"),
             Mode::Split => write!(f, "Split"),
+            Mode::PlainText => write!(f, "Plain text"),
         }
     }
 }
 
+/// Something happened to a document's file on disk that the user has to decide about.
+#[derive(Debug, Clone)]
+enum DiskChange {
+    /// Contents changed while we have unsaved edits.
+    Modified,
+    Deleted,
+    Renamed(PathBuf),
+    /// We lost write permission while we have unsaved edits.
+    ReadOnly,
+}
+
+/// What to try again after a file operation failed in a way the user can do something about.
+#[derive(Debug, Clone)]
+enum Retry {
+    Open(PathBuf),
+    /// Save the document to the path.
+    Save(DocumentId, PathBuf),
+    /// Move the document's file to the path.
+    Move(DocumentId, PathBuf),
+    Reload(DocumentId),
+}
+
 // define state
 struct Montagne {
-    content: text_editor::Content,
-    items: Vec<markdown::Item>,
-    active_file: Option<PathBuf>,
+    /// Open documents in tab order, never empty.
+    documents: Vec<Document>,
+    /// Index of the active document in `documents`.
+    active: usize,
+
+    /// Settings outside of any workspace, new documents start with these.
+    user_config: Config,
+    /// Backups of the active file, while the backups dialog is open.
+    backups: Option<Vec<Backup>>,
+
+    /// How many documents our journal file holds.
+    journal_entries: usize,
+    /// Unsaved work left behind by previous runs, offered one at a time.
+    recoveries: Vec<Recovery>,
+
+    window_size: Option<Size>,
+    window_position: Option<Point>,
 
     theme: Theme,
 
-    is_loading: bool,
-    is_dirty: bool,
+    /// The open file dialog is up.
+    is_opening: bool,
 
-    application_mode: Mode,
-    application_msg: String,
+    /// What happened lately, the latest in the status bar.
+    notifications: Notifications,
+    is_show_notifications: bool,
 
     is_show_exit_modal: bool,
-    is_show_file_changed_modal: bool,
+    /// The active tab is being closed with unsaved changes.
+    is_show_close_tab_modal: bool,
+    is_show_encodings: bool,
+    is_show_line_endings: bool,
+    /// New name for the active file, while the rename dialog is open.
+    rename_to: Option<String>,
+
+    /// Started with `--wait`: whoever started us waits for `waiting_for` to be closed.
+    is_waiting: bool,
+    waiting_for: Vec<DocumentId>,
+
+    /// Our socket, when we are the Montagne other invocations hand their files to.
+    instance: Option<Instance>,
+    /// Other invocations started with `--wait`, each waiting for its documents to be closed.
+    waiters: Vec<(Vec<DocumentId>, Waiter)>,
+
+    /// Files being dragged over the window.
+    hovered_files: Vec<PathBuf>,
+
+    recent: RecentFiles,
+    is_show_recent: bool,
+    recent_filter: String,
+    /// Index of the file Enter opens, among those matching `recent_filter`.
+    recent_selected: usize,
+
+    /// Folder open in the sidebar.
+    workspace: Option<Workspace>,
+    is_show_sidebar: bool,
+    /// Question about a workspace entry, and the name typed in answer.
+    workspace_prompt: Option<(Prompt, String)>,
+
+    /// Files held in a loading tab until the user says whether to open them, asked one at a time.
+    open_warnings: Vec<(DocumentId, DocumentSession, OpenWarning)>,
+    /// A file operation that failed, and what trying again means.
+    failure: Option<(Error, Retry)>,
 }
 
 impl Montagne {
-    fn new() -> (Self, Task<Message>) {
+    fn new(
+        session: Session,
+        recent: RecentFiles,
+        args: Args,
+        instance: Option<Instance>,
+    ) -> (Self, Task<Message>) {
         let theme = Theme::KanagawaDragon;
-        (
-            Self {
-                content: text_editor::Content::new(),
-                items: markdown::parse("").collect(),
-                active_file: None,
-                theme: theme,
-                is_loading: false,
-                is_dirty: false,
-                application_mode: Mode::Write,
-                application_msg: String::from("Welcome to Montagne."),
-                is_show_exit_modal: false,
-                is_show_file_changed_modal: false,
-            },
-            // change later to reload tabs (or previously opened editors)
-            Task::none(),
-        )
+
+        let mut documents = Vec::new();
+        let mut tasks = vec![
+            Task::perform(Config::load_for(None), Message::UserConfigLoaded),
+            Task::perform(recover(), Message::JournalRecovered),
+        ];
+
+        // files asked for on the command line take the place of the last session
+        let restored = if args.files.is_empty() {
+            session.documents.as_slice()
+        } else {
+            &[]
+        };
+
+        // the tabs are there right away, in their order, and fill in as the files are read
+        // the user config isn't read yet, the limits of large files are the defaults like for
+        // files on the command line
+        let large_files = Config::default().large_files;
+
+        for saved in restored.iter().cloned() {
+            let mut document = Document::new(Config::default());
+            document.path = Some(saved.path.clone());
+            document.mode = args.mode.clone().unwrap_or_else(|| saved.mode.clone());
+            document.is_read_only = args.readonly;
+            document.is_loading = true;
+
+            let id = document.id;
+            tasks.push(Task::perform(
+                open_checked(saved.path.clone(), large_files.clone()),
+                move |result| Message::SessionFileLoaded(id, saved.clone(), result),
+            ));
+
+            documents.push(document);
+        }
+
+        let active = session.active.min(documents.len().saturating_sub(1));
+
+        if documents.is_empty() {
+            let mut document = Document::new(Config::default());
+            document.mode = args.mode.clone().unwrap_or(Mode::Write);
+            documents.push(document);
+        }
+
+        let mut montagne = Self {
+            documents,
+            active,
+            user_config: Config::default(),
+            backups: None,
+            journal_entries: 0,
+            recoveries: Vec::new(),
+            window_size: session.window.map(|geometry| geometry.size()),
+            window_position: None,
+            theme,
+            is_opening: false,
+            notifications: Notifications::default(),
+            is_show_notifications: false,
+            is_show_exit_modal: false,
+            is_show_close_tab_modal: false,
+            is_show_encodings: false,
+            is_show_line_endings: false,
+            rename_to: None,
+            is_waiting: args.wait,
+            waiting_for: Vec::new(),
+            instance,
+            waiters: Vec::new(),
+            hovered_files: Vec::new(),
+            recent,
+            is_show_recent: false,
+            recent_filter: String::new(),
+            recent_selected: 0,
+            workspace: None,
+            is_show_sidebar: false,
+            workspace_prompt: None,
+            open_warnings: Vec::new(),
+            failure: None,
+        };
+
+        if let Some(root) = session.workspace.filter(|_| args.files.is_empty()) {
+            tasks.push(montagne.open_folder(root));
+        }
+
+        let (opened, documents) = montagne.open_requested(&args);
+        tasks.push(opened);
+
+        if args.wait {
+            montagne.waiting_for = documents;
+        }
+
+        (montagne, Task::batch(tasks))
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::WindowEvent(window::Event::CloseRequested) => {
-                if self.is_dirty {
-                    self.application_msg = "Close Requested".to_string();
+                if self.documents.iter().any(|document| document.is_dirty) {
+                    self.notifications.info("Close Requested");
                     self.is_show_exit_modal = true;
                     widget::focus_next()
                 } else {
                     Task::done(Message::CloseApp)
                 }
             }
+            Message::WindowEvent(window::Event::Opened { position, size }) => {
+                self.window_position = position;
+                self.window_size = Some(size);
+                Task::none()
+            }
+            Message::WindowEvent(window::Event::Moved(position)) => {
+                self.window_position = Some(position);
+                Task::none()
+            }
+            Message::WindowEvent(window::Event::Resized(size)) => {
+                self.window_size = Some(size);
+                Task::none()
+            }
+            Message::WindowEvent(window::Event::FileHovered(path)) => {
+                self.hovered_files.push(path);
+                Task::none()
+            }
+            Message::WindowEvent(window::Event::FilesHoveredLeft) => {
+                self.hovered_files.clear();
+                Task::none()
+            }
+            Message::WindowEvent(window::Event::FileDropped(path)) => {
+                self.hovered_files.clear();
+                self.drop_file(path)
+            }
             Message::WindowEvent(_) => Task::none(),
-            Message::CloseApp => window::get_latest().and_then(window::close),
+            Message::CloseApp => {
+                // leaving on purpose, there is nothing to recover next time
+                forget_journal();
+                // a run for `$EDITOR` is a side trip, keep the last session for the next real start
+                if !self.is_waiting {
+                    self.session().save();
+                }
+                // whoever waits on us sees the connection close
+                if let Some(instance) = &self.instance {
+                    instance.remove();
+                }
+                window::get_latest().and_then(window::close)
+            }
             Message::CloseExitModal => {
-                self.application_msg = "Request Cancelled".to_string();
+                self.notifications.info("Request Cancelled");
                 self.is_show_exit_modal = false;
                 Task::none()
             }
             Message::Edit(action) => {
-                if self.is_show_exit_modal {
+                if self.is_show_exit_modal || self.is_show_close_tab_modal {
+                    return Task::none();
+                }
+
+                let document = &mut self.documents[self.active];
+
+                if document.is_read_only && action.is_edit() {
+                    self.notifications.warn(format!(
+                        "{} is read-only, unlock it to edit",
+                        document.name()
+                    ));
                     return Task::none();
                 }
 
-                self.is_dirty = self.is_dirty || action.is_edit();
+                document.perform(action)
+            }
+            Message::Undo | Message::Redo => {
+                let document = &mut self.documents[self.active];
 
-                self.content.perform(action);
+                if document.is_read_only {
+                    self.notifications.warn(format!(
+                        "{} is read-only, unlock it to edit",
+                        document.name()
+                    ));
+                    return Task::none();
+                }
 
-                if matches!(self.application_mode, Mode::Split | Mode::Preview) {
-                    self.items = markdown::parse(&self.content.text()).collect();
+                if document.merge.is_some() {
+                    self.notifications.warn("Finish the merge first");
+                    return Task::none();
                 }
 
+                let is_undo = matches!(message, Message::Undo);
+                let changed = if is_undo {
+                    document.undo()
+                } else {
+                    document.redo()
+                };
+
+                if !changed {
+                    self.notifications.info(if is_undo {
+                        "Nothing to undo"
+                    } else {
+                        "Nothing to redo"
+                    });
+                }
+                Task::none()
+            }
+            Message::EditsPaused(id, generation) => match self.position(id) {
+                Some(index) => self.documents[index].edits_paused(generation),
+                None => Task::none(),
+            },
+            Message::Parsed(id, generation, items) => {
+                if let Some(index) = self.position(id) {
+                    self.documents[index].parsed(generation, items);
+                }
                 Task::none()
             }
-            Message::FileModified => {
-                if self.is_dirty {
-                    self.is_show_file_changed_modal = true;
+            Message::FileModified(id, stamp) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+                let document = &mut self.documents[index];
+
+                if document.disk_stamp == Some(stamp) {
+                    // our own write, nothing changed for the user
+                    return Task::none();
+                }
+
+                if document.is_loading {
+                    // decide once the save (or load) in flight has landed
+                    document.pending_disk_stamp = Some(stamp);
+                    return Task::none();
+                }
+
+                let was_read_only = document
+                    .disk_stamp
+                    .is_some_and(|known| known.is_read_only());
+                let is_same_contents = document
+                    .disk_stamp
+                    .is_some_and(|known| known.same_contents(&stamp));
+
+                if is_same_contents {
+                    // only the permissions changed
+                    document.disk_stamp = Some(stamp);
+
+                    if stamp.is_read_only() && !was_read_only {
+                        self.notifications
+                            .warn(format!("{} is now read-only", document.name()));
+                        if document.is_dirty {
+                            document.disk_change = Some(DiskChange::ReadOnly);
+                        } else {
+                            document.follow_disk_permissions();
+                        }
+                    } else if !stamp.is_read_only() && was_read_only {
+                        document.follow_disk_permissions();
+                        if matches!(document.disk_change, Some(DiskChange::ReadOnly)) {
+                            document.disk_change = None;
+                        }
+                        self.notifications
+                            .info(format!("{} is writable again", document.name()));
+                    }
+
+                    return Task::none();
+                }
+
+                if document.is_dirty {
+                    document.disk_change = Some(DiskChange::Modified);
                     Task::none()
                 } else {
-                    self.load_active_file_or_set_error()
+                    self.notifications
+                        .info(format!("{} changed on disk, reloading", document.name()));
+                    self.reload(index)
                 }
             }
-            Message::LoadFile => {
-                self.is_loading = true;
-                self.load_active_file_or_set_error()
+            Message::FileDeleted(id) => {
+                if let Some(index) = self.position(id)
+                    && !self.documents[index].is_moving
+                    && self.documents[index]
+                        .path
+                        .as_ref()
+                        .is_some_and(|path| !path.exists())
+                {
+                    self.set_file_missing(index, DiskChange::Deleted);
+                }
+                Task::none()
             }
-            Message::NewFile => {
-                if !self.is_loading {
-                    self.active_file = None;
-                    self.content = text_editor::Content::new();
-                    // optionally check what mode the file is opened with
-                    if matches!(&self.application_mode, Mode::Preview | Mode::Split) {
-                        self.items = markdown::parse(&self.content.text()).collect();
-                    }
+            Message::FileRenamed(id, destination) => {
+                if let Some(index) = self.position(id)
+                    && !self.documents[index].is_moving
+                    && self.documents[index]
+                        .path
+                        .as_ref()
+                        .is_some_and(|path| !path.exists())
+                {
+                    self.set_file_missing(index, DiskChange::Renamed(destination));
                 }
+                Task::none()
+            }
+            Message::FollowRename(destination) => {
+                self.notifications
+                    .info(format!("Following file to {}", destination.display()));
 
+                let document = self.document_mut();
+                document.path = Some(destination.clone());
+                document.disk_change = None;
+
+                let id = document.id;
+                Task::perform(FileStamp::of(destination), move |stamp| {
+                    Message::FollowedRename(id, stamp)
+                })
+            }
+            Message::FollowedRename(id, stamp) => {
+                if let Some(index) = self.position(id) {
+                    self.documents[index].disk_stamp = stamp;
+                }
                 Task::none()
             }
-            Message::OpenFile => {
-                if self.is_loading {
-                    Task::none()
-                } else {
-                    self.is_loading = true;
+            Message::OpenMerge => {
+                let document = self.document_mut();
+
+                if document.is_loading {
+                    return Task::none();
+                }
+
+                match &document.path {
+                    Some(path) => {
+                        let id = document.id;
+                        let path = path.clone();
 
-                    Task::perform(open_file(), Message::FileOpened)
+                        document.is_loading = true;
+                        document.disk_change = None;
+                        Task::perform(load_file(path), move |result| {
+                            Message::MergeLoaded(id, result)
+                        })
+                    }
+                    None => Task::none(),
                 }
             }
-            Message::FileOpened(result) => {
-                self.is_loading = false;
+            Message::MergeLoaded(id, result) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+                let document = &mut self.documents[index];
+
+                document.is_loading = false;
 
                 match result {
-                    Err(Error::DialogClosed) => {
-                        self.application_msg = "Dialogue closed".to_string();
+                    Ok(file) => {
+                        let merge = Merge::new(
+                            &document.base_text,
+                            file.text,
+                            file.stamp,
+                            &document.text(),
+                        );
+
+                        self.notifications.info(match merge.conflict_count() {
+                            0 => "Merged without conflicts, review and apply".to_string(),
+                            conflicts => format!("{} conflicts to resolve", conflicts),
+                        });
+                        document.merge = Some(merge);
                     }
-                    Err(Error::IoError(kind)) => {
-                        self.application_msg = format!("I/O Error {}", kind);
-                        eprint!("{}", kind)
+                    Err(error) if error.is_not_found() => {
+                        self.set_file_missing(index, DiskChange::Deleted);
                     }
-                    Ok((path, content)) => {
-                        self.is_dirty = false;
-                        self.content = text_editor::Content::with_text(&content);
-                        self.active_file = Some(path);
-                        self.application_msg = "File Opened".to_string();
+                    Err(Error::DialogClosed) => {}
+                    Err(error) => {
+                        self.notifications.error(error.to_string());
+                        document.disk_change = Some(DiskChange::Modified);
+                    }
+                }
 
-                        if matches!(&self.application_mode, Mode::Preview | Mode::Split) {
-                            self.items = markdown::parse(&self.content.text()).collect();
-                        }
+                Task::none()
+            }
+            Message::ResolveHunk(index, side) => {
+                if let Some(merge) = &mut self.document_mut().merge {
+                    merge.choose(index, side);
+                }
+                Task::none()
+            }
+            Message::ApplyMerge => {
+                let document = self.document_mut();
+
+                let Some(merge) = document.merge.take() else {
+                    return Task::none();
+                };
+
+                let merged = merge.text();
+
+                document.replace_text(&merged);
+                // the merge now stands in for what we loaded: the file on disk
+                document.is_dirty = merged != *merge.disk_text;
+                document.base_text = merge.disk_text;
+                document.disk_stamp = merge.disk_stamp;
+                document.is_journal_stale = true;
+                self.notifications.info("Merge applied");
+
+                self.settle_pending_disk_change(self.active)
+            }
+            Message::CancelMerge => {
+                let document = self.document_mut();
+                document.merge = None;
+                // the file on disk is still different, go back to the question
+                document.disk_change = Some(DiskChange::Modified);
+                Task::none()
+            }
+            Message::DismissDiskChange => {
+                self.document_mut().disk_change = None;
+                Task::none()
+            }
+            Message::CloseFile => {
+                self.is_show_close_tab_modal = false;
+                self.notifications.info("File closed");
+                self.close_tab(self.active)
+            }
+            Message::LoadFile => self.reload(self.active),
+            Message::FileReloaded(id, result) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+                let document = &mut self.documents[index];
+
+                document.is_loading = false;
+                let previous_file = document.path.clone();
+
+                match result {
+                    Err(error)
+                        if error.is_not_found()
+                            && document.path.as_ref().is_some_and(|path| !path.exists()) =>
+                    {
+                        // reloading a file that vanished, keep the buffer instead of losing it
+                        self.set_file_missing(index, DiskChange::Deleted);
+                    }
+                    Err(error) => {
+                        self.fail(error, Some(Retry::Reload(id)));
+                    }
+                    Ok(file) => {
+                        document.load(file);
+                        self.notifications.info(format!(
+                            "{} reloaded as {}",
+                            document.name(),
+                            document.encoding
+                        ));
                     }
                 }
 
+                Task::batch([
+                    self.settle_pending_disk_change(index),
+                    self.reload_config_if_moved(index, previous_file),
+                ])
+            }
+            Message::NewFile => {
+                self.documents.push(Document::new(self.user_config.clone()));
+                self.select(self.documents.len() - 1);
                 Task::none()
             }
-            Message::SaveFile => {
-                if self.is_loading {
+            Message::OpenFile => {
+                if self.is_opening {
                     Task::none()
                 } else {
-                    self.is_loading = true;
+                    self.is_opening = true;
 
                     Task::perform(
-                        save_file(self.active_file.clone(), self.content.text()),
-                        Message::FileSaved,
+                        open_file(
+                            self.user_config.files.clone(),
+                            self.user_config.large_files.clone(),
+                        ),
+                        Message::FileOpened,
                     )
                 }
             }
-            Message::FileSaved(result) => {
-                self.is_loading = false;
+            Message::FileOpened(result) => {
+                self.is_opening = false;
 
                 match result {
                     Err(Error::DialogClosed) => {
-                        self.application_msg = "Dialogue closed".to_string();
+                        self.notifications.info("Dialogue closed");
+                        Task::none()
+                    }
+                    Err(Error::Unconfirmed(path, warning)) => {
+                        let requested = DocumentSession {
+                            path,
+                            cursor: (0, 0),
+                            mode: Mode::Write,
+                        };
+
+                        self.confirm_open(None, requested, warning);
+                        Task::none()
                     }
-                    Err(Error::IoError(kind)) => {
-                        self.application_msg = format!("I/O Error {}", kind)
+                    Err(error) => {
+                        let retry = error.path().map(|path| Retry::Open(path.to_path_buf()));
+                        self.fail(error, retry);
+                        Task::none()
                     }
-                    Ok(path) => {
-                        self.active_file = Some(path);
-                        self.is_dirty = false; // is_dirty becomes false only when we know it for sure
-                        self.application_msg = "File Saved".to_string();
+                    Ok(file) => {
+                        let remember = self.remember(&file.path);
 
-                        // also close the exit modal if we saved from there
-                        self.is_show_exit_modal = false;
+                        if let Some(index) = self
+                            .documents
+                            .iter()
+                            .position(|document| document.path.as_ref() == Some(&file.path))
+                        {
+                            self.select(index);
+                            self.notifications.info("File is already open");
+                            return remember;
+                        }
+
+                        let index = self.blank_or_new_tab();
+                        self.documents[index].load(file);
+                        self.select(index);
+                        self.notifications.info("File Opened");
+
+                        Task::batch([self.load_config(index), remember])
                     }
                 }
+            }
+            Message::SaveFile => {
+                let document = self.document();
+                self.save(self.active, document.path.clone(), document.encoding)
+            }
+            Message::SaveFileAs => self.save(self.active, None, self.document().encoding),
+            Message::SaveCopy => {
+                let document = self.document();
+                let text = document.text();
+                let mixed_line_endings = document.line_endings_of(Arc::new(text.clone()));
 
-                Task::none()
+                Task::perform(
+                    save_file(
+                        None,
+                        text,
+                        document.encoding,
+                        document.line_ending,
+                        mixed_line_endings,
+                        document.config.backup.clone(),
+                        document.config.files.clone(),
+                    ),
+                    Message::CopySaved,
+                )
             }
-            Message::LinkClicked(link) => {
-                let _ = open::that_in_background(link.to_string());
+            Message::CopySaved(result) => {
+                match result {
+                    Ok((path, _)) => self
+                        .notifications
+                        .info(format!("Saved a copy to {}", path.display())),
+                    Err(Error::DialogClosed) => {}
+                    Err(error) => self.notifications.error(error.to_string()),
+                }
                 Task::none()
             }
-            Message::SetMode(mode) => {
-                if matches!(mode, Mode::Preview | Mode::Split) {
-                    self.items = markdown::parse(&self.content.text()).collect();
+            Message::ShowRename => {
+                if self.document().path.is_none() {
+                    return Task::none();
                 }
 
-                self.application_msg = format!("{} mode", mode);
-
-                self.application_mode = mode;
-
+                self.rename_to = Some(self.document().name());
+                text_input::focus(rename_input_id())
+            }
+            Message::RenameInputChanged(name) => {
+                self.rename_to = Some(name);
                 Task::none()
             }
-            Message::TogglerToggled => {
-                if matches!(self.application_mode, Mode::Preview | Mode::Write) {
-                    Task::done(Message::SetMode(Mode::Split))
-                } else {
-                    Task::done(Message::SetMode(Mode::Write))
+            Message::CancelRename => {
+                self.rename_to = None;
+                Task::none()
+            }
+            Message::ConfirmRename => {
+                let Some(name) = self.rename_to.take() else {
+                    return Task::none();
+                };
+                let name = name.trim();
+
+                if !is_valid_name(name) {
+                    self.notifications
+                        .warn(format!("{} isn't a valid file name", name));
+                    return Task::none();
+                }
+
+                match &self.document().path {
+                    Some(path) => {
+                        let destination = path.with_file_name(name);
+                        self.move_file(self.active, Some(destination))
+                    }
+                    None => Task::none(),
                 }
             }
-        }
-    }
+            Message::MoveFile => self.move_file(self.active, None),
+            Message::FileMoved(id, result) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+                let document = &mut self.documents[index];
 
-    fn subscription(&self) -> Subscription<Message> {
-        let window_events = window::events().map(|(_id, event)| Message::WindowEvent(event));
+                document.is_loading = false;
+                document.is_moving = false;
+                let previous_file = document.path.clone();
 
-        Subscription::batch([window_events])
-    }
+                match result {
+                    Err(error @ Error::MoveFailed { .. })
+                        if error.kind() == Some(io::ErrorKind::AlreadyExists) =>
+                    {
+                        self.notifications
+                            .warn(format!("{}, pick another name", error));
+                        Task::none()
+                    }
+                    Err(error) => {
+                        let retry = match &error {
+                            Error::MoveFailed { to, .. } => Some(Retry::Move(id, to.clone())),
+                            _ => None,
+                        };
+                        self.fail(error, retry);
+                        Task::none()
+                    }
+                    Ok((path, stamp)) => {
+                        if let Some(previous) = &previous_file {
+                            self.recent.rename(previous, &path);
+                        }
 
-    fn view(&self) -> Element<'_, Message> {
-        // Top Content
-        let header = {
-            let mut menu_bar = row![
-                action(new_icon(), "New file", Some(Message::NewFile)),
-                action(
-                    open_icon(),
-                    "Open file",
-                    (!self.is_loading).then_some(Message::OpenFile)
-                ),
-                action(
-                    save_icon(),
-                    "Save file",
-                    (self.is_dirty).then_some(Message::SaveFile)
-                ),
-                horizontal_space()
-            ]
-            .align_y(Alignment::Center);
+                        document.path = Some(path);
+                        document.disk_stamp = stamp;
+                        document.disk_change = None;
+                        // the journal knows the unsaved work under the old path
+                        document.is_journal_stale = document.is_dirty;
+                        self.notifications
+                            .info(format!("Moved to {}", document.name()));
 
-            menu_bar = match &self.application_mode {
-                Mode::Write => {
-                    menu_bar.push(button("Preview").on_press(Message::SetMode(Mode::Preview)))
-                }
-                Mode::Preview => {
-                    menu_bar.push(button("Write").on_press(Message::SetMode(Mode::Write)))
+                        Task::batch([
+                            self.settle_pending_disk_change(index),
+                            self.reload_config_if_moved(index, previous_file),
+                            self.save_recent(),
+                        ])
+                    }
                 }
-                Mode::Split => menu_bar,
-            };
-
-            menu_bar = menu_bar.push(
-                toggler(matches!(self.application_mode, Mode::Split))
-                    .label("Split")
-                    .on_toggle(|_| Message::TogglerToggled),
-            );
+            }
+            Message::SaveAll => {
+                let dirty: Vec<_> = (0..self.documents.len())
+                    .filter(|&index| self.documents[index].is_dirty)
+                    .collect();
 
-            menu_bar
-        };
+                let saves: Vec<_> = dirty
+                    .into_iter()
+                    .map(|index| {
+                        let document = &self.documents[index];
+                        self.save(index, document.path.clone(), document.encoding)
+                    })
+                    .collect();
 
-        // Main Content
-        let main = {
-            let text_editor_input = text_editor(&self.content)
-                .height(Length::Fill)
-                .highlight("md", highlighter::Theme::InspiredGitHub)
-                .on_action(Message::Edit);
+                // one at a time, so the save dialogs of new files don't pile up
+                saves
+                    .into_iter()
+                    .fold(Task::none(), |saves, save| saves.chain(save))
+            }
+            Message::FileSaved(id, result) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+                let document = &mut self.documents[index];
 
-            let mut preview = scrollable(
-                markdown(
-                    &self.items,
-                    markdown::Settings::default(),
-                    markdown::Style::from_palette(Theme::TokyoNight.palette()),
-                )
-                .map(Message::LinkClicked),
-            )
-            .spacing(10)
-            .height(Length::Fill);
+                document.is_loading = false;
+                let saved_text = document.saving_text.take();
+                let saved_encoding = document.saving_encoding.take();
+                let saved_line_endings = document.saving_line_endings.take();
+                let previous_file = document.path.clone();
 
-            let main_content = match &self.application_mode {
-                Mode::Write => row![text_editor_input],
-                Mode::Preview => {
-                    preview = preview.style(preview_scrollable_style);
+                match result {
+                    Err(Error::DialogClosed) => {
+                        document.close_after_save = false;
+                        self.notifications.info("Dialogue closed");
+                    }
+                    Err(error) => {
+                        document.close_after_save = false;
+                        let retry = error.path().map(|path| Retry::Save(id, path.to_path_buf()));
+                        self.fail(error, retry);
+                    }
+                    Ok((path, stamp)) => {
+                        self.recent.add(&path);
+                        document.path = Some(path);
+                        document.disk_stamp = stamp;
+                        // saved elsewhere, where it can be written
+                        document.follow_disk_permissions();
+                        document.base_text = saved_text.unwrap_or_default();
+                        document.encoding = saved_encoding.unwrap_or(document.encoding);
+                        document.mixed_line_endings = saved_line_endings;
+                        document.is_rewrite_pending = false;
+                        // whatever was typed while the save was in flight is still unsaved
+                        document.refresh_dirty();
+                        document.disk_change = None;
+                        self.notifications
+                            .info(format!("{} saved", document.name()));
+
+                        // also close the exit modal if we saved the last unsaved document from there
+                        if self.documents.iter().all(|document| !document.is_dirty) {
+                            self.is_show_exit_modal = false;
+                        }
+                    }
+                }
+
+                let mut tasks = vec![
+                    self.settle_pending_disk_change(index),
+                    self.reload_config_if_moved(index, previous_file),
+                    self.save_recent(),
+                ];
+
+                // after the rest, which goes by the index
+                if self.documents[index].close_after_save {
+                    tasks.push(self.close_tab(index));
+                }
+
+                Task::batch(tasks)
+            }
+            Message::SessionFileLoaded(id, saved, result) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+
+                match result {
+                    Ok(file) => {
+                        self.notifications.info("Session restored");
+                        self.show_loaded(index, file, &saved)
+                    }
+                    Err(Error::Unconfirmed(_, warning)) => {
+                        self.confirm_open(Some(index), saved, warning);
+                        Task::none()
+                    }
+                    Err(_) => {
+                        self.notifications.warn(format!(
+                            "{} from your last session is no longer available",
+                            saved.path.display()
+                        ));
+                        self.close_tab(index)
+                    }
+                }
+            }
+            Message::RequestedFileLoaded(id, requested, result) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+
+                match result {
+                    Ok(file) => {
+                        self.notifications.info("File Opened");
+                        let remember = self.remember(&file.path);
+                        Task::batch([self.show_loaded(index, file, &requested), remember])
+                    }
+                    Err(error) if error.is_not_found() => {
+                        // an empty document under the asked for path, the first save creates it
+                        self.documents[index].is_loading = false;
+                        self.notifications.info(format!(
+                            "{} is a new file, saving creates it",
+                            requested.path.display()
+                        ));
+                        self.load_config(index)
+                    }
+                    Err(Error::Unconfirmed(_, warning)) => {
+                        self.confirm_open(Some(index), requested, warning);
+                        Task::none()
+                    }
+                    Err(error) => {
+                        self.fail(error, Some(Retry::Open(requested.path)));
+                        self.close_tab(index)
+                    }
+                }
+            }
+            Message::UserConfigLoaded(config) => {
+                // documents outside of any workspace may have started out with the defaults
+                for document in &mut self.documents {
+                    if document.path.is_none() {
+                        document.config = config.clone();
+                    }
+                }
+                self.user_config = config;
+                // the file types shown may have changed
+                self.refresh_workspace()
+            }
+            Message::ConfigLoaded(id, config) => {
+                if let Some(index) = self.position(id) {
+                    let document = &mut self.documents[index];
+                    document.config = config;
+                    // also lands whenever the document's path changed
+                    document.apply_file_type();
+                }
+                Task::none()
+            }
+            Message::ShowBackups => match &self.document().path {
+                Some(path) => Task::perform(list_backups(path.clone()), Message::BackupsListed),
+                None => Task::none(),
+            },
+            Message::BackupsListed(backups) => {
+                self.backups = Some(backups);
+                Task::none()
+            }
+            Message::RestoreBackup(path) => {
+                self.backups = None;
+
+                if self.document().is_read_only {
+                    self.notifications.warn(format!(
+                        "{} is read-only, unlock it to restore a backup",
+                        self.document().name()
+                    ));
+                    return Task::none();
+                }
+
+                let id = self.document().id;
+                Task::perform(load_file(path), move |result| {
+                    Message::BackupRestored(id, result)
+                })
+            }
+            Message::BackupRestored(id, result) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+                let document = &mut self.documents[index];
+
+                match result {
+                    Ok(file) => {
+                        // only the buffer changes, saving the restored version is up to the user
+                        document.replace_text(&file.text);
+                        document.refresh_dirty();
+                        document.is_journal_stale = true;
+                        self.notifications
+                            .info("Backup restored, save to keep it, or undo it");
+                    }
+                    Err(error) => self.notifications.error(error.to_string()),
+                }
+
+                Task::none()
+            }
+            Message::CloseBackups => {
+                self.backups = None;
+                Task::none()
+            }
+            Message::JournalTick => {
+                let dirty = self.documents.iter().filter(|document| document.is_dirty);
+
+                let needs_write = dirty.clone().any(|document| document.is_journal_stale)
+                    || dirty.clone().count() != self.journal_entries;
+
+                if !needs_write {
+                    return Task::none();
+                }
+
+                let entries: Vec<_> = dirty
+                    .map(|document| JournalEntry::new(document.path.clone(), document.text()))
+                    .collect();
+
+                for document in &mut self.documents {
+                    document.is_journal_stale = false;
+                }
+                self.journal_entries = entries.len();
+
+                Task::perform(write_journal(entries), Message::JournalWritten)
+            }
+            Message::JournalWritten(result) => {
+                if let Err(error) = result {
+                    self.notifications
+                        .error(format!("Could not write recovery journal: {}", error));
+                }
+                Task::none()
+            }
+            Message::JournalRecovered(recoveries) => {
+                self.recoveries = recoveries;
+                Task::none()
+            }
+            Message::RecoverJournal => {
+                if self.recoveries.is_empty() {
+                    return Task::none();
+                }
+
+                let recovery = self.recoveries.remove(0);
+
+                let index = self.blank_or_new_tab();
+                let document = &mut self.documents[index];
+
+                document.set_text(&recovery.entry.text);
+                document.path = recovery.entry.path;
+                document.base_text = recovery.disk_text.unwrap_or_default();
+                document.disk_stamp = recovery.disk_stamp;
+                document.encoding = recovery.encoding;
+                document.line_ending = recovery.line_ending;
+                // a file gone since is only back once saved
+                document.is_rewrite_pending =
+                    document.path.is_some() && document.disk_stamp.is_none();
+                document.refresh_dirty();
+                document.is_journal_stale = true;
+
+                self.select(index);
+                self.notifications.info("Unsaved changes recovered");
+
+                if !self
+                    .recoveries
+                    .iter()
+                    .any(|other| other.journal == recovery.journal)
+                {
+                    discard_journal(&recovery.journal);
+                }
+
+                self.load_config(index)
+            }
+            Message::DiscardRecovery => {
+                if self.recoveries.is_empty() {
+                    return Task::none();
+                }
+
+                let recovery = self.recoveries.remove(0);
+
+                if !self
+                    .recoveries
+                    .iter()
+                    .any(|other| other.journal == recovery.journal)
+                {
+                    discard_journal(&recovery.journal);
+                }
+
+                Task::none()
+            }
+            Message::SelectTab(id) => {
+                if let Some(index) = self.position(id) {
+                    self.select(index);
+                }
+                Task::none()
+            }
+            Message::NextTab => {
+                self.select((self.active + 1) % self.documents.len());
+                Task::none()
+            }
+            Message::PreviousTab => {
+                self.select((self.active + self.documents.len() - 1) % self.documents.len());
+                Task::none()
+            }
+            Message::MoveTab(offset) => {
+                let destination = self
+                    .active
+                    .saturating_add_signed(offset)
+                    .min(self.documents.len() - 1);
+
+                let document = self.documents.remove(self.active);
+                self.documents.insert(destination, document);
+                self.active = destination;
+                Task::none()
+            }
+            Message::CloseTab(id) => match self.position(id) {
+                Some(index) => self.request_close_tab(index),
+                None => Task::none(),
+            },
+            Message::CloseActiveTab => self.request_close_tab(self.active),
+            Message::SaveAndCloseTab => {
+                self.is_show_close_tab_modal = false;
+                self.document_mut().close_after_save = true;
+
+                let document = self.document();
+                self.save(self.active, document.path.clone(), document.encoding)
+            }
+            Message::CancelCloseTab => {
+                self.is_show_close_tab_modal = false;
+                Task::none()
+            }
+            Message::ShowEncodings => {
+                self.is_show_encodings = true;
+                Task::none()
+            }
+            Message::CloseEncodings => {
+                self.is_show_encodings = false;
+                Task::none()
+            }
+            Message::ReopenWithEncoding(encoding) => {
+                self.is_show_encodings = false;
+
+                let document = self.document_mut();
+
+                match &document.path {
+                    Some(path) if !document.is_dirty && !document.is_loading => {
+                        let id = document.id;
+                        let path = path.clone();
+
+                        document.is_loading = true;
+                        Task::perform(reopen_file(path, encoding), move |result| {
+                            Message::FileReloaded(id, result)
+                        })
+                    }
+                    _ => Task::none(),
+                }
+            }
+            Message::SaveWithEncoding(encoding) => {
+                self.is_show_encodings = false;
+                self.save(self.active, self.document().path.clone(), encoding)
+            }
+            Message::ShowLineEndings => {
+                self.is_show_line_endings = true;
+                Task::none()
+            }
+            Message::CloseLineEndings => {
+                self.is_show_line_endings = false;
+                Task::none()
+            }
+            Message::SetLineEnding(line_ending) => {
+                self.is_show_line_endings = false;
+
+                let document = self.document_mut();
+
+                if document.line_ending != line_ending || document.mixed_line_endings.is_some() {
+                    document.line_ending = line_ending;
+                    document.mixed_line_endings = None;
+                    // the file on disk has to be rewritten to match
+                    document.is_rewrite_pending = true;
+                    document.is_dirty = true;
+                    document.is_journal_stale = true;
+                    self.notifications
+                        .info(format!("Line endings convert to {} on save", line_ending));
+                }
+
+                Task::none()
+            }
+            Message::InstanceRequested(args, waiter) => {
+                let (opened, documents) = self.open_requested(&args);
+
+                match waiter {
+                    Some(waiter) if documents.is_empty() => waiter.release(),
+                    Some(waiter) => self.waiters.push((documents, waiter)),
+                    None => {}
+                }
+
+                let raise = window::get_latest().and_then(|id| {
+                    Task::batch([window::minimize(id, false), window::gain_focus(id)])
+                });
+
+                Task::batch([opened, raise])
+            }
+            Message::AssetCopied(id, result) => {
+                let Some(index) = self.position(id) else {
+                    return Task::none();
+                };
+
+                match result {
+                    Ok(image) => self.insert_image_link(index, &image),
+                    Err(error) => {
+                        self.notifications
+                            .error(format!("Could not copy the image: {}", error));
+                        Task::none()
+                    }
+                }
+            }
+            Message::ShowRecent => {
+                self.is_show_recent = true;
+                self.recent_filter.clear();
+                self.recent_selected = 0;
+                text_input::focus(recent_filter_id())
+            }
+            Message::CloseRecent => {
+                self.is_show_recent = false;
+                Task::none()
+            }
+            Message::RecentFilterChanged(filter) => {
+                self.recent_filter = filter;
+                self.recent_selected = 0;
+                Task::none()
+            }
+            Message::MoveRecentSelection(offset) => {
+                let count = self.recent.matching(&self.recent_filter).len();
+
+                if count > 0 {
+                    self.recent_selected = self
+                        .recent_selected
+                        .saturating_add_signed(offset)
+                        .min(count - 1);
+                }
+                Task::none()
+            }
+            Message::OpenSelectedRecent => {
+                match self
+                    .recent
+                    .matching(&self.recent_filter)
+                    .get(self.recent_selected)
+                {
+                    Some(file) => Task::done(Message::OpenRecent(file.path.clone())),
+                    None => Task::none(),
+                }
+            }
+            Message::OpenRecent(path) => {
+                self.is_show_recent = false;
+
+                if let Some(index) = self
+                    .documents
+                    .iter()
+                    .position(|document| document.path.as_ref() == Some(&path))
+                {
+                    self.select(index);
+                    return self.remember(&path);
+                }
+
+                let large_files = self.user_config.large_files.clone();
+                Task::perform(open_checked(path.clone(), large_files), move |result| {
+                    Message::RecentOpened(path.clone(), result)
+                })
+            }
+            Message::RecentOpened(path, result) => match result {
+                result @ (Ok(_) | Err(Error::Unconfirmed(..))) => {
+                    Task::done(Message::FileOpened(result))
+                }
+                Err(error) if error.is_not_found() => {
+                    self.recent.remove(&path);
+                    self.notifications.warn(format!(
+                        "{} no longer exists, removed it from recent files",
+                        path.display()
+                    ));
+                    self.save_recent()
+                }
+                Err(error) => {
+                    self.fail(error, Some(Retry::Open(path)));
+                    Task::none()
+                }
+            },
+            Message::TogglePinRecent(path) => {
+                self.recent.toggle_pin(&path);
+                self.save_recent()
+            }
+            Message::RemoveRecent(path) => {
+                self.recent.remove(&path);
+                self.recent_selected = 0;
+                self.save_recent()
+            }
+            Message::RemoveMissingRecent => Task::perform(
+                find_missing(self.recent.paths()),
+                Message::RecentMissingFound,
+            ),
+            Message::RecentMissingFound(missing) => {
+                for path in &missing {
+                    self.recent.remove(path);
+                }
+                self.recent_selected = 0;
+
+                if missing.is_empty() {
+                    self.notifications.info("All recent files are still there");
+                    Task::none()
+                } else {
+                    self.notifications
+                        .info(format!("Removed {} missing recent files", missing.len()));
+                    self.save_recent()
+                }
+            }
+            Message::RecentSaved(result) => {
+                if let Err(error) = result {
+                    self.notifications
+                        .error(format!("Could not save recent files: {}", error));
+                }
+                Task::none()
+            }
+            Message::OpenFolder => Task::perform(pick_folder(), Message::FolderPicked),
+            Message::FolderPicked(Some(root)) => self.open_folder(root),
+            Message::FolderPicked(None) => {
+                self.notifications.info("Dialogue closed");
+                Task::none()
+            }
+            Message::WorkspaceLoaded(root, result) => {
+                if let Some(workspace) = &mut self.workspace
+                    && workspace.root == root
+                {
+                    match result {
+                        Ok(nodes) => workspace.nodes = nodes,
+                        Err(error) => self.notifications.error(error),
+                    }
+                }
+                Task::none()
+            }
+            Message::WorkspaceChanged => self.refresh_workspace(),
+            Message::CloseFolder => {
+                self.workspace = None;
+                self.workspace_prompt = None;
+                Task::none()
+            }
+            Message::ToggleSidebar => {
+                if self.workspace.is_some() {
+                    self.is_show_sidebar = !self.is_show_sidebar;
+                }
+                Task::none()
+            }
+            Message::WorkspaceEntryClicked(path) => {
+                let Some(workspace) = &mut self.workspace else {
+                    return Task::none();
+                };
+
+                workspace.selected = Some(path.clone());
+
+                if workspace.is_folder(&path) {
+                    workspace.toggle(&path);
+                    Task::none()
+                } else if is_image(&path) {
+                    let _ = open::that_in_background(&path);
+                    Task::none()
+                } else if let Some(index) = self
+                    .documents
+                    .iter()
+                    .position(|document| document.path.as_ref() == Some(&path))
+                {
+                    self.select(index);
+                    Task::none()
+                } else {
+                    let large_files = self.user_config.large_files.clone();
+                    Task::perform(open_checked(path, large_files), Message::FileOpened)
+                }
+            }
+            Message::WorkspaceNewFile | Message::WorkspaceNewFolder => {
+                let Some(workspace) = &self.workspace else {
+                    return Task::none();
+                };
+
+                let folder = workspace.target_folder();
+                let prompt = match message {
+                    Message::WorkspaceNewFile => Prompt::NewFile(folder),
+                    _ => Prompt::NewFolder(folder),
+                };
+
+                self.workspace_prompt = Some((prompt, String::new()));
+                text_input::focus(workspace_input_id())
+            }
+            Message::WorkspaceRename => {
+                let Some(selected) = self.workspace.as_ref().and_then(|w| w.selected.clone())
+                else {
+                    return Task::none();
+                };
+
+                let name = selected
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+
+                self.workspace_prompt = Some((Prompt::Rename(selected), name));
+                text_input::focus(workspace_input_id())
+            }
+            Message::WorkspaceDelete => {
+                if let Some(selected) = self.workspace.as_ref().and_then(|w| w.selected.clone()) {
+                    self.workspace_prompt = Some((Prompt::Delete(selected), String::new()));
+                }
+                Task::none()
+            }
+            Message::WorkspaceMove => {
+                match self.workspace.as_ref().and_then(|w| w.selected.clone()) {
+                    Some(selected) => {
+                        Task::perform(pick_destination(selected.clone()), move |folder| {
+                            Message::WorkspaceDestinationPicked(selected.clone(), folder)
+                        })
+                    }
+                    None => Task::none(),
+                }
+            }
+            Message::WorkspaceDestinationPicked(path, Some(folder)) => match path.file_name() {
+                Some(name) => {
+                    let destination = folder.join(name);
+                    self.move_workspace_entry(path, destination)
+                }
+                None => Task::none(),
+            },
+            Message::WorkspaceDestinationPicked(_, None) => {
+                self.notifications.info("Dialogue closed");
+                Task::none()
+            }
+            Message::WorkspaceNameChanged(name) => {
+                if let Some((_, typed)) = &mut self.workspace_prompt {
+                    *typed = name;
+                }
+                Task::none()
+            }
+            Message::CancelWorkspacePrompt => {
+                self.workspace_prompt = None;
+                Task::none()
+            }
+            Message::ConfirmWorkspacePrompt => {
+                let Some((prompt, name)) = self.workspace_prompt.take() else {
+                    return Task::none();
+                };
+                let name = name.trim();
+
+                if !matches!(prompt, Prompt::Delete(_)) && !is_valid_name(name) {
+                    self.notifications
+                        .warn(format!("{} isn't a valid name", name));
+                    return Task::none();
+                }
+
+                match prompt {
+                    Prompt::NewFile(folder) => {
+                        let mut path = folder.join(name);
+                        // notes unless said otherwise
+                        if path.extension().is_none() {
+                            path.set_extension("md");
+                        }
+                        Task::perform(create_file(path), Message::TreeChanged)
+                    }
+                    Prompt::NewFolder(folder) => {
+                        Task::perform(create_folder(folder.join(name)), Message::TreeChanged)
+                    }
+                    Prompt::Rename(path) => {
+                        let destination = path.with_file_name(name);
+                        self.move_workspace_entry(path, destination)
+                    }
+                    Prompt::Delete(path) => Task::perform(delete_entry(path), Message::TreeChanged),
+                }
+            }
+            Message::TreeChanged(result) => {
+                let task = match result {
+                    Err(error) => {
+                        for document in &mut self.documents {
+                            document.is_moving = false;
+                        }
+                        self.notifications.error(error);
+                        Task::none()
+                    }
+                    Ok(TreeChange::Created(path)) => self.workspace_created(path),
+                    Ok(TreeChange::Moved { from, to }) => self.workspace_moved(&from, &to),
+                    Ok(TreeChange::Deleted(path)) => self.workspace_deleted(&path),
+                };
+
+                // the watcher would get there too, a bit later
+                Task::batch([task, self.refresh_workspace()])
+            }
+            Message::OpenAnyway => {
+                if self.open_warnings.is_empty() {
+                    return Task::none();
+                }
+
+                let (id, requested, _) = self.open_warnings.remove(0);
+                if self.position(id).is_none() {
+                    return Task::none();
+                }
+
+                Task::run(
+                    load_with_progress(requested.path.clone()),
+                    move |loading| match loading {
+                        Loading::Progress(progress) => Message::LoadProgress(id, progress),
+                        Loading::Done(result) => {
+                            Message::RequestedFileLoaded(id, requested.clone(), result)
+                        }
+                    },
+                )
+            }
+            Message::SkipOpen => {
+                if self.open_warnings.is_empty() {
+                    return Task::none();
+                }
+
+                let (id, _, _) = self.open_warnings.remove(0);
+                match self.position(id) {
+                    Some(index) => self.close_tab(index),
+                    None => Task::none(),
+                }
+            }
+            Message::LoadProgress(id, progress) => {
+                if let Some(index) = self.position(id) {
+                    self.documents[index].load_progress = Some(progress);
+                }
+                Task::none()
+            }
+            Message::LinkClicked(link) => {
+                let _ = open::that_in_background(link.to_string());
+                Task::none()
+            }
+            Message::SetMode(_) | Message::TogglerToggled
+                if matches!(self.document().mode, Mode::PlainText) =>
+            {
+                self.notifications
+                    .warn("Only markdown files have a preview");
+                Task::none()
+            }
+            Message::SetMode(mode) => {
+                self.notifications.info(format!("{} mode", mode));
+
+                let document = self.document_mut();
+                document.mode = mode;
+                document.parse();
+
+                Task::none()
+            }
+            Message::RetryFailed => {
+                let Some((_, retry)) = self.failure.take() else {
+                    return Task::none();
+                };
+
+                match retry {
+                    Retry::Open(path) => {
+                        let args = Args {
+                            files: vec![FileArg { path, cursor: None }],
+                            ..Args::default()
+                        };
+
+                        self.open_requested(&args).0
+                    }
+                    Retry::Save(id, path) => match self.position(id) {
+                        Some(index) => {
+                            let encoding = self.documents[index].encoding;
+                            self.save(index, Some(path), encoding)
+                        }
+                        None => Task::none(),
+                    },
+                    Retry::Move(id, path) => match self.position(id) {
+                        Some(index) => self.move_file(index, Some(path)),
+                        None => Task::none(),
+                    },
+                    Retry::Reload(id) => match self.position(id) {
+                        Some(index) => self.reload(index),
+                        None => Task::none(),
+                    },
+                }
+            }
+            Message::SaveFailedAs => match self.failure.take() {
+                Some((_, Retry::Save(id, _))) => match self.position(id) {
+                    Some(index) => {
+                        let encoding = self.documents[index].encoding;
+                        self.save(index, None, encoding)
+                    }
+                    None => Task::none(),
+                },
+                _ => Task::none(),
+            },
+            Message::DismissFailure => {
+                self.failure = None;
+                Task::none()
+            }
+            Message::ShowNotifications => {
+                self.is_show_notifications = true;
+                Task::none()
+            }
+            Message::CloseNotifications => {
+                self.is_show_notifications = false;
+                Task::none()
+            }
+            Message::AcknowledgeNotification(id) => {
+                self.notifications.acknowledge(id);
+                Task::none()
+            }
+            Message::AcknowledgeAllNotifications => {
+                self.notifications.acknowledge_all();
+                Task::none()
+            }
+            Message::ClearNotifications => {
+                self.notifications.clear();
+                Task::none()
+            }
+            Message::NotificationTick => Task::none(),
+            Message::ToggleReadOnly => {
+                let document = &mut self.documents[self.active];
+                document.is_read_only = !document.is_read_only;
+                // the user's call from now on
+                document.is_locked_by_disk = false;
+
+                self.notifications.info(if document.is_read_only {
+                    format!("{} is locked", document.name())
+                } else if document.is_read_only_on_disk() {
+                    format!(
+                        "{} can be edited, save it elsewhere to keep the changes",
+                        document.name()
+                    )
+                } else {
+                    format!("{} can be edited", document.name())
+                });
+                Task::none()
+            }
+            Message::TogglerToggled => {
+                if matches!(self.document().mode, Mode::Preview | Mode::Write) {
+                    Task::done(Message::SetMode(Mode::Split))
+                } else {
+                    Task::done(Message::SetMode(Mode::Write))
+                }
+            }
+        }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let window_events = window::events().map(|(_id, event)| Message::WindowEvent(event));
+
+        let file_watchers =
+            self.documents.iter().filter_map(|document| {
+                let path = document.path.clone()?;
+
+                Some(montagne_watcher::watch(path).with(document.id).map(
+                    |(id, change)| match change {
+                        Change::Modified(stamp) => Message::FileModified(id, stamp),
+                        Change::Deleted => Message::FileDeleted(id),
+                        Change::Renamed(destination) => Message::FileRenamed(id, destination),
+                    },
+                ))
+            });
+
+        let journal = iced::time::every(JOURNAL_INTERVAL).map(|_| Message::JournalTick);
+
+        // redraw while a toast waits to go away by itself
+        let toast_timer = if self.notifications.is_expiring() {
+            iced::time::every(TOAST_DURATION / 5).map(|_| Message::NotificationTick)
+        } else {
+            Subscription::none()
+        };
+
+        let instance = match &self.instance {
+            Some(instance) => instance
+                .listen()
+                .map(|(args, waiter)| Message::InstanceRequested(args, waiter)),
+            None => Subscription::none(),
+        };
+
+        let shortcuts = keyboard::on_key_press(|key, modifiers| shortcut(&key, modifiers));
+
+        let workspace = match &self.workspace {
+            Some(workspace) => {
+                watch_folder(workspace.root.clone()).map(|_| Message::WorkspaceChanged)
+            }
+            None => Subscription::none(),
+        };
+
+        let picker_keys = if self.is_show_recent {
+            keyboard::on_key_press(picker_key)
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch(
+            [
+                window_events,
+                journal,
+                toast_timer,
+                shortcuts,
+                picker_keys,
+                instance,
+                workspace,
+            ]
+            .into_iter()
+            .chain(file_watchers),
+        )
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let document = self.document();
+
+        // Top Content
+        let header = {
+            let mut menu_bar = row![
+                action(new_icon(), "New file", Some(Message::NewFile)),
+                action(
+                    open_icon(),
+                    "Open file",
+                    (!self.is_opening).then_some(Message::OpenFile)
+                ),
+                action(
+                    save_icon(),
+                    "Save file",
+                    (document.is_dirty).then_some(Message::SaveFile)
+                ),
+                button("Recent").on_press(Message::ShowRecent),
+                button("Open folder").on_press(Message::OpenFolder),
+                horizontal_space()
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center);
+
+            if self.workspace.is_some() {
+                menu_bar = menu_bar.push(button("Sidebar").on_press(Message::ToggleSidebar));
+            }
+
+            menu_bar = menu_bar
+                .push(button("Save as").on_press(Message::SaveFileAs))
+                .push(button("Save a copy").on_press(Message::SaveCopy));
+
+            menu_bar = menu_bar.push(
+                button(if document.is_read_only {
+                    "🔒 Read-only"
+                } else {
+                    "Lock"
+                })
+                .style(if document.is_read_only {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .on_press(Message::ToggleReadOnly),
+            );
+
+            if document.path.is_some() {
+                menu_bar = menu_bar
+                    .push(button("Rename").on_press(Message::ShowRename))
+                    .push(button("Move").on_press(Message::MoveFile))
+                    .push(button("Backups").on_press(Message::ShowBackups));
+            }
+
+            menu_bar = match &document.mode {
+                Mode::Write => {
+                    menu_bar.push(button("Preview").on_press(Message::SetMode(Mode::Preview)))
+                }
+                Mode::Preview => {
+                    menu_bar.push(button("Write").on_press(Message::SetMode(Mode::Write)))
+                }
+                Mode::Split | Mode::PlainText => menu_bar,
+            };
+
+            if !matches!(document.mode, Mode::PlainText) {
+                menu_bar = menu_bar.push(
+                    toggler(matches!(document.mode, Mode::Split))
+                        .label("Split")
+                        .on_toggle(|_| Message::TogglerToggled),
+                );
+            }
+
+            menu_bar
+        };
+
+        let tabs = tab_bar(&self.documents, self.active);
+
+        // Main Content
+        let main: Element<'_, Message> = if let Some(merge) = &document.merge {
+            merge_view(merge)
+        } else {
+            let text_editor_input = text_editor(&document.content)
+                .height(Length::Fill)
+                .highlight(&document.syntax(), highlighter::Theme::InspiredGitHub)
+                .key_binding(editor_key_binding)
+                .on_action(Message::Edit);
+
+            let mut preview = scrollable(
+                markdown(
+                    &document.items,
+                    markdown::Settings::default(),
+                    markdown::Style::from_palette(Theme::TokyoNight.palette()),
+                )
+                .map(Message::LinkClicked),
+            )
+            .spacing(10)
+            .height(Length::Fill);
+
+            let main_content = match &document.mode {
+                Mode::Write | Mode::PlainText => row![text_editor_input],
+                Mode::Preview => {
+                    preview = preview.style(preview_scrollable_style);
                     row![center(
                         container(preview).width(Length::Shrink).max_width(800)
                     )]
@@ -313,68 +1889,761 @@ impl Montagne {
                 Mode::Split => row![text_editor_input, preview],
             };
 
-            main_content.spacing(10)
+            main_content.spacing(10).into()
+        };
+
+        let main: Element<'_, Message> = match &self.workspace {
+            Some(workspace) if self.is_show_sidebar => {
+                row![file_tree(workspace, document.path.as_deref()), main]
+                    .spacing(10)
+                    .into()
+            }
+            _ => main,
         };
 
         // Bottom Content
         let status_bar = {
             let position = {
-                let (ln, col) = self.content.cursor_position();
+                let (ln, col) = document.content.cursor_position();
 
                 text(format!("Ln {}, Col {}", ln + 1, col + 1))
             };
 
-            let path_text = match &self.active_file {
-                Some(path) => path
-                    .file_name()
-                    .and_then(|name| name.to_str())
-                    .map(|name| name.to_string())
-                    .unwrap_or_else(|| "Unnamed file".to_string()),
-                None => String::from("New file"),
-            };
+            let filename = text(document.name());
 
-            let filename = text(path_text);
+            // a large file takes a while, show how far along it is
+            let progress = document.load_progress.map(|progress| {
+                row![
+                    text("Loading"),
+                    progress_bar(0.0..=1.0, progress).width(100)
+                ]
+                .spacing(5)
+                .align_y(Alignment::Center)
+            });
 
-            row![
-                position,
-                horizontal_space(),
-                text(&self.application_msg),
-                horizontal_space(),
-                filename
-            ]
+            let large = document
+                .is_large
+                .then(|| text("Large file, no preview or highlighting"));
+
+            let encoding = button(text(document.encoding.to_string()))
+                .style(button::text)
+                .padding(0)
+                .on_press(Message::ShowEncodings);
+
+            let line_ending = button(text(if document.mixed_line_endings.is_some() {
+                format!("Mixed ({})", document.line_ending)
+            } else {
+                document.line_ending.to_string()
+            }))
+            .style(button::text)
+            .padding(0)
+            .on_press(Message::ShowLineEndings);
+
+            row![position]
+                .push_maybe(progress)
+                .push(horizontal_space())
+                .push(summary(&self.notifications))
+                .push(horizontal_space())
+                .push_maybe(large)
+                .push(line_ending)
+                .push(encoding)
+                .push(filename)
+                .spacing(20)
         };
 
         // App Display
-        let app = container(column![header, main, status_bar])
+        let app = container(column![header, tabs, main, status_bar].spacing(5))
             .padding(Padding::from([5, 5]))
             .style(editor_style);
 
-        if self.is_show_exit_modal {
-            exit_modal(app)
-        } else if self.is_show_file_changed_modal {
-            match &self.active_file {
-                Some(path) => file_changed_modal(app, path.clone()),
-                None => app.into()
+        let app = toasts(app, self.notifications.toasts());
+
+        if !self.hovered_files.is_empty() {
+            drop_modal(app, &self.hovered_files)
+        } else if let Some((error, retry)) = &self.failure {
+            // whatever was being done waits for the answer, saving before exit included
+            file_error_modal(app, error, matches!(retry, Retry::Save(..)))
+        } else if self.is_show_exit_modal {
+            let dirty = self
+                .documents
+                .iter()
+                .filter(|document| document.is_dirty)
+                .map(Document::name)
+                .collect();
+
+            exit_modal(app, dirty)
+        } else if self.is_show_close_tab_modal {
+            close_tab_modal(app, document.name())
+        } else if let Some((_, requested, warning)) = self.open_warnings.first() {
+            open_warning_modal(app, &requested.path, *warning, self.open_warnings.len())
+        } else if let Some(name) = &self.rename_to {
+            rename_modal(app, name)
+        } else if let Some((prompt, name)) = &self.workspace_prompt {
+            workspace_prompt_modal(app, prompt, name)
+        } else if self.is_show_notifications {
+            notifications_modal(app, &self.notifications)
+        } else if self.is_show_recent {
+            recent_modal(
+                app,
+                self.recent.matching(&self.recent_filter),
+                &self.recent_filter,
+                self.recent_selected,
+            )
+        } else if let Some(recovery) = self.recoveries.first() {
+            recovery_modal(app, recovery, self.recoveries.len())
+        } else if let Some(backups) = &self.backups {
+            backups_modal(app, backups)
+        } else if self.is_show_encodings {
+            // reopening would throw the edits away
+            let can_reopen = document.path.is_some() && !document.is_dirty && !document.is_loading;
+
+            encodings_modal(app, document.encoding, can_reopen)
+        } else if self.is_show_line_endings {
+            line_endings_modal(
+                app,
+                document.line_ending,
+                document.mixed_line_endings.is_some(),
+            )
+        } else if let (Some(change), Some(path)) = (&document.disk_change, &document.path) {
+            match change {
+                DiskChange::Modified => file_changed_modal(app, path.clone()),
+                DiskChange::Deleted => file_missing_modal(app, path.clone(), None),
+                DiskChange::Renamed(destination) => {
+                    file_missing_modal(app, path.clone(), Some(destination.clone()))
+                }
+                DiskChange::ReadOnly => file_read_only_modal(app, path.clone()),
             }
-        }
-         else {
-            app.into()
+        } else {
+            app
         }
     }
 
     fn theme(&self) -> Theme {
         self.theme.clone()
     }
+
+    /// The active document as the `[title]` config of its workspace says.
+    fn title(&self) -> String {
+        let document = self.document();
+
+        document.config.title.title(TitleParts {
+            name: document.name(),
+            path: document.path.as_deref(),
+            workspace: self.workspace.as_ref().map(Workspace::name),
+            mode: document.mode.to_string(),
+            is_dirty: document.is_dirty,
+        })
+    }
 }
 
 impl Montagne {
-    fn load_active_file_or_set_error(&mut self) -> Task<Message> {
-        match &self.active_file {
-            Some(path) => Task::perform(load_file(path.clone()), Message::FileOpened),
+    fn document(&self) -> &Document {
+        &self.documents[self.active]
+    }
+
+    fn document_mut(&mut self) -> &mut Document {
+        &mut self.documents[self.active]
+    }
+
+    /// Where the document is in the tab order, if it is still open.
+    fn position(&self, id: DocumentId) -> Option<usize> {
+        self.documents.iter().position(|document| document.id == id)
+    }
+
+    fn select(&mut self, index: usize) {
+        self.active = index;
+        // these dialogs belong to the tab we are leaving
+        self.backups = None;
+        self.is_show_close_tab_modal = false;
+        self.is_show_encodings = false;
+        self.is_show_line_endings = false;
+        self.rename_to = None;
+    }
+
+    /// Put `path` at the top of the recent files.
+    fn remember(&mut self, path: &Path) -> Task<Message> {
+        self.recent.add(path);
+        self.save_recent()
+    }
+
+    fn save_recent(&self) -> Task<Message> {
+        Task::perform(self.recent.clone().save(), Message::RecentSaved)
+    }
+
+    /// The active tab when nothing was typed in it yet, otherwise a new tab.
+    fn blank_or_new_tab(&mut self) -> usize {
+        if self.document().is_blank() {
+            self.active
+        } else {
+            self.documents.push(Document::new(self.user_config.clone()));
+            self.documents.len() - 1
+        }
+    }
+
+    /// Ask the user what to do about a failed file operation they can retry, otherwise just say
+    /// what went wrong.
+    fn fail(&mut self, error: Error, retry: Option<Retry>) {
+        match retry {
+            Some(retry) if error.is_retryable() => {
+                self.notifications
+                    .record(Severity::Error, error.to_string());
+                self.failure = Some((error, retry));
+            }
+            _ => self.notifications.error(error.to_string()),
+        }
+    }
+
+    /// Keep a file in a loading tab of its own until the user says whether to open it anyway.
+    fn confirm_open(
+        &mut self,
+        index: Option<usize>,
+        requested: DocumentSession,
+        warning: OpenWarning,
+    ) {
+        let index = index.unwrap_or_else(|| {
+            let index = self.blank_or_new_tab();
+            let document = &mut self.documents[index];
+
+            document.path = Some(requested.path.clone());
+            document.is_loading = true;
+            index
+        });
+
+        self.select(index);
+        self.open_warnings
+            .push((self.documents[index].id, requested, warning));
+    }
+
+    /// Close the tab, or ask first when it has unsaved changes.
+    fn request_close_tab(&mut self, index: usize) -> Task<Message> {
+        if self.documents[index].is_dirty {
+            self.select(index);
+            self.is_show_close_tab_modal = true;
+            Task::none()
+        } else {
+            self.close_tab(index)
+        }
+    }
+
+    /// Close the tab, throwing away its buffer. The last tab is replaced by a new document.
+    fn close_tab(&mut self, index: usize) -> Task<Message> {
+        let document = self.documents.remove(index);
+        self.open_warnings.retain(|(id, _, _)| *id != document.id);
+
+        if self.documents.is_empty() {
+            self.documents.push(Document::new(self.user_config.clone()));
+        }
+
+        if index < self.active || self.active == self.documents.len() {
+            self.active -= 1;
+        }
+
+        self.waiters.retain_mut(|(documents, waiter)| {
+            documents.retain(|id| *id != document.id);
+
+            if documents.is_empty() {
+                waiter.release();
+            }
+            !documents.is_empty()
+        });
+
+        let was_waited_for = self.waiting_for.contains(&document.id);
+        self.waiting_for.retain(|id| *id != document.id);
+
+        if was_waited_for && self.waiting_for.is_empty() {
+            // done with what we were started for, leave the way closing the window does
+            Task::done(Message::WindowEvent(window::Event::CloseRequested))
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Open the files of a command line in tabs, the cursor where asked, and show the first.
+    /// Files already open are shown as they are. Also returns the documents of the files.
+    fn open_requested(&mut self, args: &Args) -> (Task<Message>, Vec<DocumentId>) {
+        let mut tasks = Vec::new();
+        let mut documents = Vec::new();
+        let mut first = None;
+
+        for file in &args.files {
+            if file.path.is_dir() {
+                tasks.push(self.open_folder(file.path.clone()));
+                continue;
+            }
+
+            if let Some(index) = self
+                .documents
+                .iter()
+                .position(|document| document.path.as_ref() == Some(&file.path))
+            {
+                let document = &mut self.documents[index];
+                document.is_read_only = document.is_read_only || args.readonly;
+
+                if let Some(cursor) = file.cursor
+                    && !document.is_loading
+                {
+                    move_cursor(&mut document.content, cursor);
+                }
+
+                documents.push(document.id);
+                first.get_or_insert(index);
+                continue;
+            }
+
+            let requested = DocumentSession {
+                path: file.path.clone(),
+                cursor: file.cursor.unwrap_or_default(),
+                mode: args.mode.clone().unwrap_or(Mode::Write),
+            };
+
+            let index = self.blank_or_new_tab();
+            let document = &mut self.documents[index];
+
+            document.path = Some(requested.path.clone());
+            document.mode = requested.mode.clone();
+            document.is_read_only = args.readonly;
+            document.is_loading = true;
+
+            let id = document.id;
+            tasks.push(Task::perform(
+                open_checked(requested.path.clone(), self.user_config.large_files.clone()),
+                move |result| Message::RequestedFileLoaded(id, requested.clone(), result),
+            ));
+
+            documents.push(id);
+            first.get_or_insert(index);
+        }
+
+        if let Some(index) = first {
+            self.select(index);
+        }
+
+        (Task::batch(tasks), documents)
+    }
+
+    /// Images dropped on the window get linked from the active document, other files open
+    /// in a tab of their own.
+    fn drop_file(&mut self, path: PathBuf) -> Task<Message> {
+        if !is_image(&path) {
+            let args = Args {
+                files: vec![FileArg { path, cursor: None }],
+                ..Args::default()
+            };
+
+            let (opened, _) = self.open_requested(&args);
+            return opened;
+        }
+
+        let document = self.document();
+
+        if document.merge.is_some() {
+            self.notifications
+                .warn("Finish the merge before adding images");
+            return Task::none();
+        }
+
+        if document.is_read_only {
+            self.notifications.warn(format!(
+                "{} is read-only, unlock it to add images",
+                document.name()
+            ));
+            return Task::none();
+        }
+
+        match &document.path {
+            Some(document_path) if document.config.assets.copy => {
+                let id = document.id;
+
+                Task::perform(
+                    copy_into_assets(path, document_path.clone(), document.config.assets.clone()),
+                    move |result| {
+                        Message::AssetCopied(id, result.map_err(|error| error.to_string()))
+                    },
+                )
+            }
+            _ => self.insert_image_link(self.active, &path),
+        }
+    }
+
+    /// Put a link to `image` at the cursor of the document.
+    fn insert_image_link(&mut self, index: usize, image: &Path) -> Task<Message> {
+        let document = &mut self.documents[index];
+        let link = image_link(image, document.path.as_deref());
+
+        let edited = document.perform(Action::Edit(Edit::Paste(Arc::new(link))));
+
+        self.notifications
+            .info(format!("Linked {}", image.display()));
+        edited
+    }
+
+    /// Fill the tab opened at `index` before the file was read, as `opened` says.
+    fn show_loaded(
+        &mut self,
+        index: usize,
+        file: LoadedFile,
+        opened: &DocumentSession,
+    ) -> Task<Message> {
+        let document = &mut self.documents[index];
+
+        document.is_loading = false;
+        document.load(file);
+        move_cursor(&mut document.content, opened.cursor);
+
+        Task::batch([
+            self.settle_pending_disk_change(index),
+            self.load_config(index),
+        ])
+    }
+
+    /// What to restore on the next start.
+    fn session(&self) -> Session {
+        // documents that were never saved have nothing to reopen
+        let saved: Vec<&Document> = self
+            .documents
+            .iter()
+            .filter(|document| document.path.is_some())
+            .collect();
+
+        let active = saved
+            .iter()
+            .position(|document| document.id == self.document().id)
+            .unwrap_or(0);
+
+        let documents = saved
+            .into_iter()
+            .filter_map(|document| {
+                let path = document.path.clone()?;
+                Some(DocumentSession {
+                    path,
+                    cursor: cursor_position(&document.content),
+                    mode: document.mode.clone(),
+                })
+            })
+            .collect();
+
+        Session {
+            documents,
+            active,
+            window: self.window_size.map(|size| WindowGeometry {
+                width: size.width,
+                height: size.height,
+                position: self
+                    .window_position
+                    .map(|position| (position.x, position.y)),
+            }),
+            workspace: self
+                .workspace
+                .as_ref()
+                .map(|workspace| workspace.root.clone()),
+        }
+    }
+
+    /// Show the folder in the sidebar, in place of the one shown.
+    fn open_folder(&mut self, root: PathBuf) -> Task<Message> {
+        self.workspace = Some(Workspace::new(root));
+        self.workspace_prompt = None;
+        self.is_show_sidebar = true;
+
+        self.refresh_workspace()
+    }
+
+    /// Read the workspace's tree again.
+    fn refresh_workspace(&self) -> Task<Message> {
+        match &self.workspace {
+            Some(workspace) => {
+                let root = workspace.root.clone();
+                let file_types = self.user_config.files.clone();
+                Task::perform(read_tree(root.clone(), file_types), move |result| {
+                    Message::WorkspaceLoaded(root.clone(), result)
+                })
+            }
+            None => Task::none(),
+        }
+    }
+
+    /// Rename or move a workspace entry, the documents in it follow once it is done.
+    fn move_workspace_entry(&mut self, from: PathBuf, to: PathBuf) -> Task<Message> {
+        for document in &mut self.documents {
+            if document
+                .path
+                .as_ref()
+                .is_some_and(|path| path.starts_with(&from))
+            {
+                document.is_moving = true;
+            }
+        }
+
+        Task::perform(move_entry(from, to), Message::TreeChanged)
+    }
+
+    /// Show what was just created: the folder with it expanded, and a new file opened.
+    fn workspace_created(&mut self, path: PathBuf) -> Task<Message> {
+        let Some(workspace) = &mut self.workspace else {
+            return Task::none();
+        };
+
+        if let Some(folder) = path.parent() {
+            workspace.expanded.insert(folder.to_path_buf());
+        }
+        workspace.selected = Some(path.clone());
+
+        self.notifications
+            .info(format!("Created {}", path.display()));
+
+        if path.is_file() {
+            Task::perform(load_file(path), Message::FileOpened)
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Documents in what moved now edit the files at their new paths.
+    fn workspace_moved(&mut self, from: &Path, to: &Path) -> Task<Message> {
+        if let Some(workspace) = &mut self.workspace {
+            workspace.follow_move(from, to);
+        }
+
+        let mut tasks = Vec::new();
+
+        for index in 0..self.documents.len() {
+            let document = &mut self.documents[index];
+            document.is_moving = false;
+
+            let Some(previous) = document.path.clone() else {
+                continue;
+            };
+            let Some(path) = moved_path(&previous, from, to) else {
+                continue;
+            };
+
+            self.recent.rename(&previous, &path);
+
+            document.path = Some(path);
+            document.disk_change = None;
+            // the journal knows the unsaved work under the old path
+            document.is_journal_stale = document.is_dirty;
+
+            tasks.push(self.reload_config_if_moved(index, Some(previous)));
+        }
+
+        self.notifications
+            .info(format!("Moved to {}", to.display()));
+        tasks.push(self.save_recent());
+
+        Task::batch(tasks)
+    }
+
+    /// Close the documents of the deleted files, unless they hold unsaved work: the usual
+    /// prompt about a deleted file shows for them.
+    fn workspace_deleted(&mut self, path: &Path) -> Task<Message> {
+        if let Some(workspace) = &mut self.workspace
+            && workspace
+                .selected
+                .as_ref()
+                .is_some_and(|selected| selected.starts_with(path))
+        {
+            workspace.selected = None;
+        }
+
+        let deleted: Vec<DocumentId> = self
+            .documents
+            .iter()
+            .filter(|document| {
+                !document.is_dirty
+                    && document
+                        .path
+                        .as_ref()
+                        .is_some_and(|document_path| document_path.starts_with(path))
+            })
+            .map(|document| document.id)
+            .collect();
+
+        self.notifications
+            .info(format!("Deleted {}", path.display()));
+
+        // closing a tab moves the ones after it, find each by its id
+        let closed: Vec<_> = deleted
+            .into_iter()
+            .filter_map(|id| {
+                let index = self.position(id)?;
+                Some(self.close_tab(index))
+            })
+            .collect();
+
+        Task::batch(closed)
+    }
+
+    /// The document's file is gone from its path: keep the buffer and ask the user what to do.
+    fn set_file_missing(&mut self, index: usize, change: DiskChange) {
+        let document = &mut self.documents[index];
+
+        document.disk_stamp = None;
+        if matches!(change, DiskChange::Deleted) {
+            // the buffer is now the only copy of the document
+            document.is_rewrite_pending = true;
+            document.is_dirty = true;
+        }
+        self.notifications
+            .warn(format!("{} no longer exists on disk", document.name()));
+        document.disk_change = Some(change);
+    }
+
+    /// The workspace settings depend on where the document lives.
+    fn load_config(&self, index: usize) -> Task<Message> {
+        let document = &self.documents[index];
+        let id = document.id;
+
+        Task::perform(Config::load_for(document.path.clone()), move |config| {
+            Message::ConfigLoaded(id, config)
+        })
+    }
+
+    fn reload_config_if_moved(
+        &self,
+        index: usize,
+        previous_file: Option<PathBuf>,
+    ) -> Task<Message> {
+        if self.documents[index].path == previous_file {
+            Task::none()
+        } else {
+            self.load_config(index)
+        }
+    }
+
+    /// Re-examine a disk change that arrived while we were busy loading or saving.
+    fn settle_pending_disk_change(&mut self, index: usize) -> Task<Message> {
+        let document = &mut self.documents[index];
+
+        match document.pending_disk_stamp.take() {
+            Some(stamp) => Task::done(Message::FileModified(document.id, stamp)),
+            None => Task::none(),
+        }
+    }
+
+    /// Read the document's file again, replacing the buffer.
+    fn reload(&mut self, index: usize) -> Task<Message> {
+        let document = &mut self.documents[index];
+
+        match &document.path {
+            Some(path) => {
+                let id = document.id;
+                let path = path.clone();
+
+                document.is_loading = true;
+                Task::perform(load_file(path), move |result| {
+                    Message::FileReloaded(id, result)
+                })
+            }
             None => {
-                self.application_msg = "Error: No file path for active file.".to_string();
+                self.notifications
+                    .error("Error: No file path for active file.");
                 Task::none()
             }
         }
     }
+
+    /// Rename or move the document's file to `destination`, or to a path picked by the user when
+    /// there is none. Unsaved changes stay in the buffer, for the file at its new path.
+    fn move_file(&mut self, index: usize, destination: Option<PathBuf>) -> Task<Message> {
+        let document = &mut self.documents[index];
+
+        let Some(path) = document.path.clone() else {
+            return Task::none();
+        };
+
+        if document.is_loading {
+            return Task::none();
+        }
+
+        document.is_loading = true;
+        document.is_moving = true;
+
+        let id = document.id;
+        let file_types = document.config.files.clone();
+        Task::perform(move_file(path, destination, file_types), move |result| {
+            Message::FileMoved(id, result)
+        })
+    }
+
+    /// Write the document to `path` as text in `encoding`, or to a path picked by the user when
+    /// there is none.
+    fn save(
+        &mut self,
+        index: usize,
+        path: Option<PathBuf>,
+        encoding: TextEncoding,
+    ) -> Task<Message> {
+        let document = &mut self.documents[index];
+
+        if document.is_loading {
+            return Task::none();
+        }
+
+        // locked, or it would only fail: ask where to put it instead
+        let path = path.filter(|_| !document.is_read_only && !document.is_read_only_on_disk());
+
+        document.is_loading = true;
+
+        let id = document.id;
+        let text = document.text();
+        let saving_text = Arc::new(text.clone());
+        document.saving_line_endings = document.line_endings_of(saving_text.clone());
+        document.saving_text = Some(saving_text);
+        document.saving_encoding = Some(encoding);
+
+        Task::perform(
+            save_file(
+                path,
+                text,
+                encoding,
+                document.line_ending,
+                document.saving_line_endings.clone(),
+                document.config.backup.clone(),
+                document.config.files.clone(),
+            ),
+            move |result| Message::FileSaved(id, result),
+        )
+    }
+}
+
+/// The line and column (counted in characters) of the cursor of `content`.
+fn cursor_position(content: &text_editor::Content) -> (usize, usize) {
+    let (line, index) = content.cursor_position();
+
+    // the editor counts bytes, count characters so the position survives edits elsewhere
+    let column = content
+        .line(line)
+        .and_then(|text| text.get(..index).map(|before| before.chars().count()))
+        .unwrap_or(0);
+
+    (line, column)
+}
+
+/// Put the cursor of `content` at `line` and `column` (counted in characters), clamped to the text.
+fn move_cursor(content: &mut text_editor::Content, (line, column): (usize, usize)) {
+    let line = line.min(content.line_count().saturating_sub(1));
+
+    content.perform(Action::Move(Motion::DocumentStart));
+
+    // once laid out, a long line wraps and takes more than one step down: go by where we are
+    while content.cursor_position().0 < line {
+        let before = content.cursor_position();
+        content.perform(Action::Move(Motion::Down));
+
+        if content.cursor_position() == before {
+            break;
+        }
+    }
+
+    content.perform(Action::Move(Motion::Home));
+
+    let length = content
+        .line(line)
+        .map(|text| text.chars().count())
+        .unwrap_or(0);
+
+    for _ in 0..column.min(length) {
+        content.perform(Action::Move(Motion::Right));
+    }
 }
@@ -1,5 +1,4 @@
 use std::path::PathBuf;
-use std::sync::Arc;
 
 use iced::{
     widget::{markdown, text_editor},
@@ -7,25 +6,179 @@ use iced::{
 };
 
 use crate::Mode;
-use crate::montagne_file_io::Error;
+use crate::montagne_backup::Backup;
+use crate::montagne_cli::Args;
+use crate::montagne_config::Config;
+use crate::montagne_document::DocumentId;
+use crate::montagne_encoding::TextEncoding;
+use crate::montagne_file_io::{Error, FileStamp, LineEnding, LoadedFile};
+use crate::montagne_instance::Waiter;
+use crate::montagne_journal::Recovery;
+use crate::montagne_merge::Side;
+use crate::montagne_session::DocumentSession;
+use crate::montagne_workspace::{Node, TreeChange};
 
 // define messages (interactions of the application)
 #[derive(Debug, Clone)]
 pub enum Message {
     Edit(text_editor::Action),
+    /// Undo the last step of editing the active document.
+    Undo,
+    Redo,
     LinkClicked(markdown::Url),
+    /// Typing paused, the document's preview is due for a parse if nothing was typed since
+    /// the edit counted.
+    EditsPaused(DocumentId, u64),
+    /// The markdown of the document, as parsed at the edit counted.
+    Parsed(DocumentId, u64, Vec<markdown::Item>),
     NewFile,
     OpenFile,
-    FileOpened(Result<(PathBuf, Arc<String>), Error>),
+    FileOpened(Result<LoadedFile, Error>),
     SaveFile,
-    FileSaved(Result<PathBuf, Error>),
+    FileSaved(DocumentId, Result<(PathBuf, Option<FileStamp>), Error>),
     SetMode(Mode),
     TogglerToggled,
+    /// Show the log of notifications.
+    ShowNotifications,
+    CloseNotifications,
+    /// Close the toast of the notification, it stays in the log.
+    AcknowledgeNotification(u64),
+    AcknowledgeAllNotifications,
+    ClearNotifications,
+    /// Redraw, so that toasts whose time is up go away.
+    NotificationTick,
+    /// Try the file operation that failed again.
+    RetryFailed,
+    /// Save the document whose save failed somewhere else.
+    SaveFailedAs,
+    DismissFailure,
+    /// Lock the active document against editing, or unlock it.
+    ToggleReadOnly,
     WindowEvent(window::Event),
     CloseApp,
     CloseExitModal,
-    FileModified, // file stored in storage has changed
+    FileModified(DocumentId, FileStamp), // file stored in storage has changed
+    FileDeleted(DocumentId),
+    FileRenamed(DocumentId, PathBuf),
+    /// Keep editing the active file at the path it was moved to.
+    FollowRename(PathBuf),
+    FollowedRename(DocumentId, Option<FileStamp>),
+    /// Compare the buffer with the changed file on disk in the merge view.
+    OpenMerge,
+    MergeLoaded(DocumentId, Result<LoadedFile, Error>),
+    /// Pick which version of the hunk at the index goes into the merged document.
+    ResolveHunk(usize, Side),
+    ApplyMerge,
+    CancelMerge,
+    /// A document of the last session was read back from disk.
+    SessionFileLoaded(DocumentId, DocumentSession, Result<LoadedFile, Error>),
+    /// A file asked for on the command line was read from disk, with the cursor and mode asked for.
+    RequestedFileLoaded(DocumentId, DocumentSession, Result<LoadedFile, Error>),
+    /// Settings for new documents.
+    UserConfigLoaded(Config),
+    ConfigLoaded(DocumentId, Config),
+    /// List the backups of the active file.
+    ShowBackups,
+    BackupsListed(Vec<Backup>),
+    /// Put the contents of the backup at the path in the buffer.
+    RestoreBackup(PathBuf),
+    BackupRestored(DocumentId, Result<LoadedFile, Error>),
+    CloseBackups,
+    /// Time to write unsaved buffers to the recovery journal.
+    JournalTick,
+    JournalWritten(Result<(), String>),
+    JournalRecovered(Vec<Recovery>),
+    /// Open the first recovery offered in a tab of its own.
+    RecoverJournal,
+    DiscardRecovery,
+    /// Close the disk change dialog and keep editing as is.
+    DismissDiskChange,
+    /// Close the active file, throwing away the buffer.
+    CloseFile,
+    /// Save the buffer under a path picked by the user.
+    SaveFileAs,
+    /// Write the buffer to a path picked by the user, and keep editing the active file.
+    SaveCopy,
+    CopySaved(Result<(PathBuf, Option<FileStamp>), Error>),
+    /// Ask for a new name for the active file.
+    ShowRename,
+    RenameInputChanged(String),
+    ConfirmRename,
+    CancelRename,
+    /// Move the active file to a path picked by the user.
+    MoveFile,
+    FileMoved(DocumentId, Result<(PathBuf, Option<FileStamp>), Error>),
     // user should either reload file or keep current changes (if is_dirty otherwise reload automatically)
     /// Load (reload) the active file.
     LoadFile,
+    FileReloaded(DocumentId, Result<LoadedFile, Error>),
+    /// Save every document with unsaved changes, one after the other.
+    SaveAll,
+    SelectTab(DocumentId),
+    NextTab,
+    PreviousTab,
+    /// Move the active tab by the offset, to the left when negative.
+    MoveTab(isize),
+    /// Close the tab, asking first when it has unsaved changes.
+    CloseTab(DocumentId),
+    CloseActiveTab,
+    SaveAndCloseTab,
+    CancelCloseTab,
+    /// Show the encodings the active file can be reopened or saved with.
+    ShowEncodings,
+    CloseEncodings,
+    /// Read the active file again, as text in the encoding.
+    ReopenWithEncoding(TextEncoding),
+    SaveWithEncoding(TextEncoding),
+    /// Show the line endings the active file can be converted to.
+    ShowLineEndings,
+    CloseLineEndings,
+    /// Convert the line endings of the active file when it is next saved.
+    SetLineEnding(LineEnding),
+    /// Another invocation of Montagne handed us its command line.
+    InstanceRequested(Args, Option<Waiter>),
+    /// An image dropped on the document was copied to the path in its assets folder.
+    AssetCopied(DocumentId, Result<PathBuf, String>),
+    /// Show the recent files picker.
+    ShowRecent,
+    CloseRecent,
+    RecentFilterChanged(String),
+    /// Move the picker's selection by the offset, up when negative.
+    MoveRecentSelection(isize),
+    OpenSelectedRecent,
+    OpenRecent(PathBuf),
+    RecentOpened(PathBuf, Result<LoadedFile, Error>),
+    TogglePinRecent(PathBuf),
+    RemoveRecent(PathBuf),
+    /// Drop the recent files that no longer exist.
+    RemoveMissingRecent,
+    RecentMissingFound(Vec<PathBuf>),
+    RecentSaved(Result<(), String>),
+    /// Pick a folder to show in the sidebar.
+    OpenFolder,
+    FolderPicked(Option<PathBuf>),
+    /// The tree under the workspace folder was read.
+    WorkspaceLoaded(PathBuf, Result<Vec<Node>, String>),
+    /// Something changed under the workspace folder.
+    WorkspaceChanged,
+    CloseFolder,
+    ToggleSidebar,
+    /// Select the entry, expanding a folder or opening a file.
+    WorkspaceEntryClicked(PathBuf),
+    WorkspaceNewFile,
+    WorkspaceNewFolder,
+    WorkspaceRename,
+    WorkspaceMove,
+    /// Move the entry into the folder picked, if any.
+    WorkspaceDestinationPicked(PathBuf, Option<PathBuf>),
+    WorkspaceDelete,
+    WorkspaceNameChanged(String),
+    ConfirmWorkspacePrompt,
+    CancelWorkspacePrompt,
+    TreeChanged(Result<TreeChange, String>),
+    /// Open the large or binary file asked about, loading it with progress.
+    OpenAnyway,
+    /// Don't open the file asked about, closing its tab.
+    SkipOpen,
+    LoadProgress(DocumentId, f32),
 }
@@ -0,0 +1,136 @@
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Deserialize;
+
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "avif"];
+
+/// The `[assets]` table of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AssetsConfig {
+    /// Copy images dropped on a document into `folder` before linking them.
+    pub copy: bool,
+    /// Relative to the document's folder.
+    pub folder: PathBuf,
+}
+
+impl Default for AssetsConfig {
+    fn default() -> Self {
+        Self {
+            copy: false,
+            folder: PathBuf::from("assets"),
+        }
+    }
+}
+
+/// Images get linked from the document rather than opened.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|image| image.eq_ignore_ascii_case(extension))
+        })
+}
+
+/// Copy `image` into the assets folder next to `document`, unless it is already in the
+/// document's folder. Returns where the image to link to is.
+pub async fn copy_into_assets(
+    image: PathBuf,
+    document: PathBuf,
+    config: AssetsConfig,
+) -> io::Result<PathBuf> {
+    let Some(folder) = document.parent() else {
+        return Ok(image);
+    };
+
+    if image.starts_with(folder) {
+        return Ok(image);
+    }
+
+    let assets = folder.join(&config.folder);
+    tokio::fs::create_dir_all(&assets).await?;
+
+    let name = image
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = image
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    // never overwrite another image, unless it is this very one dropped again
+    let mut destination = assets.join(format!("{}.{}", name, extension));
+    let mut suffix = 1;
+
+    while tokio::fs::try_exists(&destination).await? {
+        if same_contents(&image, &destination).await {
+            return Ok(destination);
+        }
+
+        destination = assets.join(format!("{}-{}.{}", name, suffix, extension));
+        suffix += 1;
+    }
+
+    tokio::fs::copy(&image, &destination).await?;
+
+    Ok(destination)
+}
+
+/// Markdown for an image, relative to the document when it has been saved somewhere.
+pub fn image_link(image: &Path, document: Option<&Path>) -> String {
+    let target = match document.and_then(Path::parent) {
+        Some(folder) => relative_path(image, folder),
+        None => image.to_path_buf(),
+    };
+
+    let alt = image
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut target = target.to_string_lossy().into_owned();
+    if cfg!(windows) {
+        // links use forward slashes, whatever the platform
+        target = target.replace('\\', "/");
+    }
+
+    if target.contains([' ', '(', ')']) {
+        format!("![{}](<{}>)", alt, target)
+    } else {
+        format!("![{}]({})", alt, target)
+    }
+}
+
+/// `path` as seen from `folder`, both absolute.
+fn relative_path(path: &Path, folder: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let folder_components: Vec<Component> = folder.components().collect();
+
+    let common = path_components
+        .iter()
+        .zip(&folder_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    // different drives on Windows, there is no way to get there relatively
+    if common == 0 {
+        return path.to_path_buf();
+    }
+
+    folder_components[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(path_components[common..].iter().copied())
+        .collect()
+}
+
+async fn same_contents(a: &Path, b: &Path) -> bool {
+    match (tokio::fs::read(a).await, tokio::fs::read(b).await) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
@@ -0,0 +1,167 @@
+use std::cmp::Reverse;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+/// Where the previous version of a file goes when we overwrite it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupMode {
+    #[default]
+    Off,
+    /// A single copy next to the file, as `file.md~`.
+    Sibling,
+    /// The last few versions, in a folder of the user's data directory.
+    Rotate,
+}
+
+/// The `[backup]` table of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub mode: BackupMode,
+    /// How many rotated copies to keep per file.
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            mode: BackupMode::Off,
+            keep: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// When the backed up version was replaced.
+    pub taken: Option<SystemTime>,
+    pub len: u64,
+}
+
+/// Keep a copy of `target` as it is now, right before it gets overwritten.
+pub async fn back_up(target: &Path, config: &BackupConfig) -> io::Result<()> {
+    match config.mode {
+        BackupMode::Off => Ok(()),
+        BackupMode::Sibling => tokio::fs::copy(target, sibling_path(target))
+            .await
+            .map(|_| ()),
+        BackupMode::Rotate => {
+            let folder = backup_folder(target).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no data directory for backups")
+            })?;
+
+            tokio::fs::create_dir_all(&folder).await?;
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+
+            tokio::fs::copy(
+                target,
+                folder.join(format!("{:016}-{}", now, file_name(target))),
+            )
+            .await?;
+
+            for old in rotated_backups(&folder)
+                .await
+                .iter()
+                .skip(config.keep.max(1))
+            {
+                let _ = tokio::fs::remove_file(&old.path).await;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Every backup we know of for `target`, newest first, whatever the current mode.
+pub async fn list_backups(target: PathBuf) -> Vec<Backup> {
+    let target = tokio::fs::canonicalize(&target).await.unwrap_or(target);
+
+    let mut backups = match backup_folder(&target) {
+        Some(folder) => rotated_backups(&folder).await,
+        None => Vec::new(),
+    };
+
+    let sibling = sibling_path(&target);
+    if let Ok(metadata) = tokio::fs::metadata(&sibling).await {
+        backups.push(Backup {
+            path: sibling,
+            taken: metadata.modified().ok(),
+            len: metadata.len(),
+        });
+    }
+
+    backups.sort_by_key(|backup| Reverse(backup.taken));
+    backups
+}
+
+/// Rotated copies in `folder`, newest first.
+async fn rotated_backups(folder: &Path) -> Vec<Backup> {
+    let mut backups = Vec::new();
+
+    let Ok(mut entries) = tokio::fs::read_dir(folder).await else {
+        return backups;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        // the name starts with the time the copy was taken
+        let Some(millis) = name
+            .split_once('-')
+            .and_then(|(millis, _)| millis.parse().ok())
+        else {
+            continue;
+        };
+
+        backups.push(Backup {
+            path: entry.path(),
+            taken: Some(UNIX_EPOCH + Duration::from_millis(millis)),
+            len: entry
+                .metadata()
+                .await
+                .map(|metadata| metadata.len())
+                .unwrap_or(0),
+        });
+    }
+
+    backups.sort_by_key(|backup| Reverse(backup.taken));
+    backups
+}
+
+fn sibling_path(target: &Path) -> PathBuf {
+    target.with_file_name(format!("{}~", file_name(target)))
+}
+
+/// One folder per file, named after a hash of its full path so that files sharing a name
+/// don't share backups.
+fn backup_folder(target: &Path) -> Option<PathBuf> {
+    // FNV-1a, the folder names have to stay the same across builds
+    let hash = target
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+    dirs::data_local_dir().map(|folder| {
+        folder
+            .join("montagne")
+            .join("backups")
+            .join(format!("{:016x}-{}", hash, file_name(target)))
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,216 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Mode;
+
+pub const USAGE: &str = "\
+Usage: montagne [OPTIONS] [FILE[:LINE[:COLUMN]]]...
+
+Opens each FILE in a tab, with the cursor at LINE and COLUMN when given.
+A FILE that doesn't exist yet is created on the first save, a folder opens in the sidebar.
+
+Options:
+  --mode <write|preview|split>  Open the files in this mode
+  --readonly                    Open the files read-only, unlock them to edit
+  -w, --wait                    Return only once the files are closed (for $EDITOR)
+  --standalone                  Don't hand the files to an already running Montagne
+  -h, --help                    Show this help";
+
+/// What Montagne was started with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Args {
+    pub files: Vec<FileArg>,
+    pub mode: Option<Mode>,
+    /// Open the files locked against editing.
+    pub readonly: bool,
+    /// Keep the process around until the files are closed, as `git commit` expects of `$EDITOR`.
+    pub wait: bool,
+    /// Open a window of our own even when Montagne is already running.
+    pub standalone: bool,
+    pub help: bool,
+}
+
+/// A file to open, and where to put the cursor in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileArg {
+    /// Absolute, so it means the same thing to whoever reads it.
+    pub path: PathBuf,
+    /// Line and column (in characters), counted from 0.
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        let mut only_files = false;
+
+        while let Some(arg) = args.next() {
+            if only_files || !arg.starts_with('-') || arg == "-" {
+                let file = FileArg::parse(&arg);
+
+                if !parsed.files.iter().any(|other| other.path == file.path) {
+                    parsed.files.push(file);
+                }
+                continue;
+            }
+
+            match arg.split_once('=') {
+                Some(("--mode", mode)) => parsed.mode = Some(parse_mode(mode)?),
+                _ => match arg.as_str() {
+                    "--" => only_files = true,
+                    "--mode" => {
+                        let mode = args.next().ok_or("--mode needs a value")?;
+                        parsed.mode = Some(parse_mode(&mode)?);
+                    }
+                    "--readonly" => parsed.readonly = true,
+                    "--wait" | "-w" => parsed.wait = true,
+                    "--standalone" => parsed.standalone = true,
+                    "--help" | "-h" => parsed.help = true,
+                    _ => return Err(format!("unknown option {}", arg)),
+                },
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl FileArg {
+    /// `notes.md`, `notes.md:12` or `notes.md:12:5`. A file whose name really ends like that
+    /// wins over the position.
+    fn parse(arg: &str) -> FileArg {
+        let whole = FileArg {
+            path: absolute(Path::new(arg)),
+            cursor: None,
+        };
+
+        if whole.path.exists() {
+            return whole;
+        }
+
+        // the path may have colons, and be a number, too
+        let (path, line, column) = match split_number(arg) {
+            Some((rest, column)) => match split_number(rest) {
+                Some((path, line)) => (path, line, Some(column)),
+                None => (rest, column, None),
+            },
+            None => return whole,
+        };
+
+        // users count from 1, the editor from 0
+        let from_one = |number: &str| number.parse::<usize>().unwrap_or(1).saturating_sub(1);
+
+        FileArg {
+            path: absolute(Path::new(path)),
+            cursor: Some((from_one(line), column.map_or(0, from_one))),
+        }
+    }
+}
+
+fn parse_mode(mode: &str) -> Result<Mode, String> {
+    match mode {
+        "write" => Ok(Mode::Write),
+        "preview" => Ok(Mode::Preview),
+        "split" => Ok(Mode::Split),
+        _ => Err(format!(
+            "unknown mode {}, expected write, preview or split",
+            mode
+        )),
+    }
+}
+
+fn is_number(part: &str) -> bool {
+    !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// `notes.md:12` into `notes.md` and `12`.
+fn split_number(arg: &str) -> Option<(&str, &str)> {
+    arg.rsplit_once(':')
+        .filter(|(path, number)| !path.is_empty() && is_number(number))
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(arg: &str) -> (PathBuf, Option<(usize, usize)>) {
+        let file = FileArg::parse(arg);
+        (file.path, file.cursor)
+    }
+
+    #[test]
+    fn a_path_alone_has_no_cursor() {
+        assert_eq!(
+            file("no-such-notes.md"),
+            (absolute(Path::new("no-such-notes.md")), None)
+        );
+    }
+
+    #[test]
+    fn line_and_column_count_from_one() {
+        let path = absolute(Path::new("no-such-notes.md"));
+
+        assert_eq!(file("no-such-notes.md:12"), (path.clone(), Some((11, 0))));
+        assert_eq!(file("no-such-notes.md:12:5"), (path.clone(), Some((11, 4))));
+        assert_eq!(file("no-such-notes.md:0:0"), (path, Some((0, 0))));
+    }
+
+    #[test]
+    fn only_numbers_at_the_end_are_a_position() {
+        assert_eq!(file("a:b:12"), (absolute(Path::new("a:b")), Some((11, 0))));
+        assert_eq!(file("a:12:b"), (absolute(Path::new("a:12:b")), None));
+        assert_eq!(file("12:3:4"), (absolute(Path::new("12")), Some((2, 3))));
+        assert_eq!(file("12:3"), (absolute(Path::new("12")), Some((2, 0))));
+        assert_eq!(file(":12"), (absolute(Path::new(":12")), None));
+        assert_eq!(file("notes.md:"), (absolute(Path::new("notes.md:")), None));
+    }
+
+    #[test]
+    fn an_existing_file_wins_over_the_position() {
+        let path = std::env::temp_dir().join(format!("montagne-{}-notes:3", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+
+        let parsed = file(path.to_str().unwrap());
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(parsed, (path, None));
+    }
+
+    #[test]
+    fn options_and_files() {
+        let args = Args::parse(
+            [
+                "--mode=split",
+                "-w",
+                "one.md:2",
+                "--",
+                "--readonly",
+                "one.md",
+            ]
+            .map(String::from),
+        )
+        .unwrap();
+
+        assert!(matches!(args.mode, Some(Mode::Split)));
+        assert!(args.wait && !args.readonly);
+        assert_eq!(
+            args.files
+                .iter()
+                .map(|file| file.path.clone())
+                .collect::<Vec<_>>(),
+            [
+                absolute(Path::new("one.md")),
+                absolute(Path::new("--readonly"))
+            ]
+        );
+
+        assert!(Args::parse(["--mode".to_string()]).is_err());
+        assert!(Args::parse(["--unknown".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::montagne_assets::AssetsConfig;
+use crate::montagne_backup::BackupConfig;
+use crate::montagne_file_types::{FileTypesConfig, LargeFilesConfig};
+use crate::montagne_title::TitleConfig;
+
+/// Name of the per-workspace settings file, looked up from a document's folder upwards.
+pub const WORKSPACE_CONFIG: &str = ".montagne.toml";
+
+/// User settings. Every field has a default so a config file only needs what it changes.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub backup: BackupConfig,
+    pub assets: AssetsConfig,
+    pub files: FileTypesConfig,
+    pub large_files: LargeFilesConfig,
+    pub title: TitleConfig,
+}
+
+impl Config {
+    /// Settings for the document at `path`: the nearest workspace config above it,
+    /// otherwise the user's own config file, otherwise the defaults.
+    pub async fn load_for(path: Option<PathBuf>) -> Config {
+        let workspace_config = path
+            .as_deref()
+            .and_then(Path::parent)
+            .into_iter()
+            .flat_map(Path::ancestors)
+            .map(|folder| folder.join(WORKSPACE_CONFIG));
+
+        let candidates = workspace_config.chain(user_config_path());
+
+        for candidate in candidates {
+            let Ok(contents) = tokio::fs::read_to_string(&candidate).await else {
+                continue;
+            };
+
+            match toml::from_str(&contents) {
+                Ok(config) => return config,
+                Err(error) => eprintln!("Ignoring {}: {}", candidate.display(), error),
+            }
+        }
+
+        Config::default()
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|folder| folder.join("montagne").join("config.toml"))
+}
@@ -0,0 +1,413 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use iced::widget::{
+    markdown,
+    text_editor::{self, Action},
+};
+use iced::{Task, task};
+
+use crate::message::Message;
+use crate::montagne_config::Config;
+use crate::montagne_encoding::TextEncoding;
+use crate::montagne_file_io::{FileStamp, LineEnding, LoadedFile, MixedLineEndings};
+use crate::montagne_merge::Merge;
+use crate::montagne_undo::{Cursor, EditKind, UndoHistory};
+use crate::{DiskChange, Mode};
+
+/// How long typing has to pause before the preview catches up.
+const PARSE_DELAY: Duration = Duration::from_millis(150);
+
+/// Identifies a document for as long as it is open, wherever its tab is moved to.
+/// Work that finishes later (loads, saves, watchers) reports back with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DocumentId(u64);
+
+impl DocumentId {
+    fn unique() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// An open document, shown in its own tab.
+pub struct Document {
+    pub id: DocumentId,
+    pub content: text_editor::Content,
+    pub items: Vec<markdown::Item>,
+    pub history: UndoHistory,
+    /// Counts the edits the preview has to catch up with, a parse of an older text is stale.
+    pub parse_generation: u64,
+    /// The parse waiting for typing to pause, or running. Dropping it cancels it.
+    pub parse_task: Option<task::Handle>,
+    pub path: Option<PathBuf>,
+    /// Stamp of the file as we last loaded or saved it.
+    pub disk_stamp: Option<FileStamp>,
+    /// A change reported by the watcher while a load or save was in flight.
+    pub pending_disk_stamp: Option<FileStamp>,
+    /// Text of the file as we last loaded or saved it, the base of a three-way merge.
+    pub base_text: Arc<String>,
+    /// Text being written by a save in flight, becomes `base_text` once it lands.
+    pub saving_text: Option<Arc<String>>,
+    /// How the file's text is stored on disk, saving writes it back the same way.
+    pub encoding: TextEncoding,
+    /// Encoding of a save in flight, becomes `encoding` once it lands.
+    pub saving_encoding: Option<TextEncoding>,
+    /// Line ending the buffer's `\n`s turn into when saving.
+    pub line_ending: LineEnding,
+    /// How each line of the file ends when not all of them end with `line_ending`. Saving leaves
+    /// the lines that weren't edited that way, the others end with `line_ending`.
+    pub mixed_line_endings: Option<MixedLineEndings>,
+    /// Line endings of a save in flight, become `mixed_line_endings` once it lands.
+    pub saving_line_endings: Option<MixedLineEndings>,
+    pub merge: Option<Merge>,
+    pub disk_change: Option<DiskChange>,
+
+    /// Settings for the document's workspace.
+    pub config: Config,
+
+    /// The buffer changed since it was last written to the journal.
+    pub is_journal_stale: bool,
+
+    pub mode: Mode,
+
+    pub is_loading: bool,
+    /// Share of the file read so far, while a large file loads.
+    pub load_progress: Option<f32>,
+    /// Too big for the preview and highlighting to keep up, edited as plain text.
+    pub is_large: bool,
+    /// The file is being renamed or moved by us, its old path disappearing is no surprise.
+    pub is_moving: bool,
+    /// The buffer differs from the file, or the file has to be written again anyway.
+    pub is_dirty: bool,
+    /// The file has to be written again whatever the buffer holds: its line endings change,
+    /// or it is gone from disk.
+    pub is_rewrite_pending: bool,
+    /// Locked against editing, because the file can't be written or because the user asked.
+    pub is_read_only: bool,
+    /// The lock is ours, put on because the file can't be written: it comes off again once it
+    /// can be.
+    pub is_locked_by_disk: bool,
+    /// Close the tab as soon as the save in flight succeeds.
+    pub close_after_save: bool,
+}
+
+impl Document {
+    /// An empty, never saved document.
+    pub fn new(config: Config) -> Self {
+        Self {
+            id: DocumentId::unique(),
+            content: text_editor::Content::new(),
+            items: markdown::parse("").collect(),
+            history: UndoHistory::default(),
+            parse_generation: 0,
+            parse_task: None,
+            path: None,
+            disk_stamp: None,
+            pending_disk_stamp: None,
+            base_text: Arc::default(),
+            saving_text: None,
+            encoding: TextEncoding::default(),
+            saving_encoding: None,
+            line_ending: LineEnding::default(),
+            mixed_line_endings: None,
+            saving_line_endings: None,
+            merge: None,
+            disk_change: None,
+            config,
+            is_journal_stale: false,
+            mode: Mode::Write,
+            is_loading: false,
+            load_progress: None,
+            is_large: false,
+            is_moving: false,
+            is_dirty: false,
+            is_rewrite_pending: false,
+            is_read_only: false,
+            is_locked_by_disk: false,
+            close_after_save: false,
+        }
+    }
+
+    /// Name shown on the tab and in the status bar.
+    pub fn name(&self) -> String {
+        match &self.path {
+            Some(path) => path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| "Unnamed file".to_string()),
+            None => String::from("New file"),
+        }
+    }
+
+    /// A new document nobody typed in yet, fine to replace with a file being opened.
+    pub fn is_blank(&self) -> bool {
+        self.path.is_none() && !self.is_dirty && !self.is_loading
+    }
+
+    /// Replace the buffer with `text`, without touching the dirty flag. There is nothing to
+    /// undo before it.
+    pub fn set_text(&mut self, text: &str) {
+        self.show_text(text);
+        self.history.reset(self.text());
+    }
+
+    /// Replace the buffer with `text` as a step that can be undone, like a restored backup.
+    pub fn replace_text(&mut self, text: &str) {
+        let content = &self.content;
+        self.history
+            .before_edit(EditKind::Single, Cursor::of(content), || {
+                buffer_text(content)
+            });
+
+        self.show_text(text);
+        self.history.after_edit(Cursor::of(&self.content));
+    }
+
+    fn show_text(&mut self, text: &str) {
+        self.content = text_editor::Content::with_text(text);
+        self.parse();
+    }
+
+    /// Apply an action of the editor, keeping track of the edits to undo.
+    pub fn perform(&mut self, action: Action) -> Task<Message> {
+        match &action {
+            Action::Edit(edit) => {
+                let content = &self.content;
+                self.history
+                    .before_edit(EditKind::of(edit), Cursor::of(content), || {
+                        buffer_text(content)
+                    });
+            }
+            Action::Scroll { .. } => {}
+            // the cursor moves, typing after it is another step
+            _ => self.history.break_group(),
+        }
+
+        let is_edit = action.is_edit();
+        self.content.perform(action);
+
+        if !is_edit {
+            return Task::none();
+        }
+
+        self.history.after_edit(Cursor::of(&self.content));
+        self.edited()
+    }
+
+    /// Undo the last step of editing, `false` when there is none.
+    pub fn undo(&mut self) -> bool {
+        let content = &self.content;
+        let Some((text, cursor)) = self.history.undo(|| buffer_text(content)) else {
+            return false;
+        };
+
+        self.show_step(&text, &cursor);
+        true
+    }
+
+    /// Redo the last step undone, `false` when there is none.
+    pub fn redo(&mut self) -> bool {
+        let content = &self.content;
+        let Some((text, cursor)) = self.history.redo(|| buffer_text(content)) else {
+            return false;
+        };
+
+        self.show_step(&text, &cursor);
+        true
+    }
+
+    /// Show the text an undo or redo went back to, undoing back to the saved text leaves
+    /// nothing to save.
+    fn show_step(&mut self, text: &str, cursor: &Cursor) {
+        self.show_text(text);
+        cursor.restore(&mut self.content);
+
+        self.is_journal_stale = true;
+        self.refresh_dirty();
+    }
+
+    /// The buffer as it goes to disk.
+    pub fn text(&self) -> String {
+        buffer_text(&self.content)
+    }
+
+    /// Show the file as it was loaded from disk.
+    pub fn load(&mut self, file: LoadedFile) {
+        self.base_text = file.text;
+        self.path = Some(file.path);
+        // before the text is shown, a large file mustn't be parsed for the preview
+        self.apply_file_type();
+
+        let text = self.base_text.clone();
+        self.set_text(&text);
+        self.disk_stamp = file.stamp;
+        self.follow_disk_permissions();
+        self.encoding = file.encoding;
+        self.line_ending = file.line_ending;
+        self.mixed_line_endings = file.mixed_line_endings;
+        self.disk_change = None;
+        self.is_dirty = false;
+        self.is_rewrite_pending = false;
+        self.load_progress = None;
+    }
+
+    /// How the lines of `text`, the buffer about to be saved, end when the file mixes them.
+    pub fn line_endings_of(&self, text: Arc<String>) -> Option<MixedLineEndings> {
+        self.mixed_line_endings
+            .as_ref()
+            .and_then(|mixed| mixed.edited(text, self.line_ending))
+    }
+
+    /// Markdown gets the preview, any other file is edited as plain text, as are large files.
+    pub fn apply_file_type(&mut self) {
+        let is_markdown = self
+            .path
+            .as_deref()
+            .is_none_or(|path| self.config.files.is_markdown(path));
+
+        self.is_large = self.base_text.len() as u64 > self.config.large_files.plain_above();
+
+        if !is_markdown || self.is_large {
+            self.mode = Mode::PlainText;
+        } else if matches!(self.mode, Mode::PlainText) {
+            self.mode = Mode::Write;
+            self.parse();
+        }
+    }
+
+    /// Lock the document while its file can't be written, and lift that lock, not one the user
+    /// put on, once it can. The user can still unlock it, to edit and save it elsewhere.
+    pub fn follow_disk_permissions(&mut self) {
+        let is_read_only_on_disk = self.is_read_only_on_disk();
+
+        if is_read_only_on_disk && !self.is_read_only {
+            self.is_read_only = true;
+            self.is_locked_by_disk = true;
+        } else if !is_read_only_on_disk && self.is_locked_by_disk {
+            self.is_read_only = false;
+            self.is_locked_by_disk = false;
+        }
+    }
+
+    /// We may not write to the file, saving has to go elsewhere.
+    pub fn is_read_only_on_disk(&self) -> bool {
+        self.disk_stamp.is_some_and(|stamp| stamp.is_read_only())
+    }
+
+    /// What the editor highlights the buffer as.
+    pub fn syntax(&self) -> String {
+        if self.is_large {
+            return "txt".to_string();
+        }
+
+        self.config.files.syntax(self.path.as_deref())
+    }
+
+    /// Work out whether there is anything to save, by comparing the buffer with the text we
+    /// last loaded or saved. Typing something and deleting it leaves nothing to save.
+    pub fn refresh_dirty(&mut self) {
+        self.is_dirty = self.is_rewrite_pending || self.text() != *self.base_text;
+    }
+
+    /// The user changed the buffer: there is something to save until typing pauses and shows
+    /// it still matches the file. The preview catches up then too.
+    pub fn edited(&mut self) -> Task<Message> {
+        self.is_journal_stale = true;
+        self.is_dirty = true;
+
+        self.edits_pause_later()
+    }
+
+    /// Keep the preview up to date, only when it is shown.
+    pub fn parse(&mut self) {
+        // whatever was on its way is older than this
+        self.parse_generation += 1;
+        self.parse_task = None;
+
+        if self.is_preview_shown() {
+            self.items = markdown::parse(&self.content.text()).collect();
+        }
+    }
+
+    /// The preview is on screen and has to follow the text. Large documents never have one.
+    fn is_preview_shown(&self) -> bool {
+        !self.is_large && matches!(self.mode, Mode::Split | Mode::Preview)
+    }
+
+    /// Wait for typing to pause before the work too slow to do on every key: parsing the
+    /// preview and comparing the buffer with the file. Another edit before then cancels it
+    /// and starts waiting again.
+    fn edits_pause_later(&mut self) -> Task<Message> {
+        self.parse_generation += 1;
+        self.parse_task = None;
+
+        let (id, generation) = (self.id, self.parse_generation);
+        let (task, handle) = Task::perform(tokio::time::sleep(PARSE_DELAY), move |_| {
+            Message::EditsPaused(id, generation)
+        })
+        .abortable();
+
+        self.parse_task = Some(handle.abort_on_drop());
+        task
+    }
+
+    /// Typing paused since the edit `generation` was counted at, catch up with the text as it
+    /// is now, parsing it off the UI thread.
+    pub fn edits_paused(&mut self, generation: u64) -> Task<Message> {
+        if generation != self.parse_generation {
+            return Task::none();
+        }
+
+        self.refresh_dirty();
+
+        if !self.is_preview_shown() {
+            self.parse_task = None;
+            return Task::none();
+        }
+
+        let id = self.id;
+        let (task, handle) = Task::perform(parse_markdown(self.content.text()), move |items| {
+            Message::Parsed(id, generation, items)
+        })
+        .abortable();
+
+        self.parse_task = Some(handle.abort_on_drop());
+        task
+    }
+
+    /// Show what a parse found, unless the text changed since it started.
+    pub fn parsed(&mut self, generation: u64, items: Vec<markdown::Item>) {
+        if generation == self.parse_generation {
+            self.items = items;
+            self.parse_task = None;
+        }
+    }
+}
+
+/// The text of `content` as it goes to disk.
+fn buffer_text(content: &text_editor::Content) -> String {
+    let mut text = content.text();
+
+    // the editor ends its text with a newline either way, keep it only if the last line
+    // really ends with one, so files without it stay that way
+    let last = content.line_count().saturating_sub(1);
+    let ends_with_newline = last > 0 && content.line(last).is_some_and(|line| line.is_empty());
+
+    if !ends_with_newline {
+        text.pop();
+    }
+
+    text
+}
+
+/// Long documents take a while to parse, keep it off the thread drawing the window.
+async fn parse_markdown(text: String) -> Vec<markdown::Item> {
+    tokio::task::spawn_blocking(move || markdown::parse(&text).collect())
+        .await
+        .unwrap_or_default()
+}
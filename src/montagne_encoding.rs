@@ -0,0 +1,307 @@
+use std::fmt;
+
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{
+    BIG5, EUC_KR, Encoding, GBK, ISO_8859_2, ISO_8859_15, KOI8_R, SHIFT_JIS, UTF_8, UTF_16BE,
+    UTF_16LE, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252,
+};
+
+/// How a file's text is stored as bytes, kept so saving writes it back the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEncoding {
+    encoding: &'static Encoding,
+    /// The file starts with a byte order mark.
+    bom: bool,
+}
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self::UTF_8
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.is_utf16(), self.bom) {
+            (false, true) => write!(f, "{} with BOM", self.encoding.name()),
+            // UTF-16 usually comes with one, only the lack of it is worth telling
+            (true, false) => write!(f, "{} without BOM", self.encoding.name()),
+            _ => write!(f, "{}", self.encoding.name()),
+        }
+    }
+}
+
+/// The text didn't make sense in the encoding asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct Malformed;
+
+/// The text has characters the encoding can't represent.
+#[derive(Debug, Clone, Copy)]
+pub struct Unmappable;
+
+impl TextEncoding {
+    pub const UTF_8: Self = Self {
+        encoding: UTF_8,
+        bom: false,
+    };
+
+    /// What the user can reopen or save a file with.
+    pub fn choices() -> Vec<Self> {
+        let with_bom = [UTF_8, UTF_16LE, UTF_16BE]
+            .into_iter()
+            .map(|encoding| Self {
+                encoding,
+                bom: true,
+            });
+
+        let legacy = [
+            WINDOWS_1252,
+            ISO_8859_15,
+            WINDOWS_1250,
+            ISO_8859_2,
+            WINDOWS_1251,
+            KOI8_R,
+            SHIFT_JIS,
+            GBK,
+            BIG5,
+            EUC_KR,
+        ]
+        .into_iter()
+        .map(|encoding| Self {
+            encoding,
+            bom: false,
+        });
+
+        std::iter::once(Self::UTF_8)
+            .chain(with_bom)
+            .chain(legacy)
+            .collect()
+    }
+
+    /// Work out how `bytes` are encoded and decode them. A byte order mark wins, then valid UTF-8,
+    /// then UTF-16 without a mark, and otherwise the most likely legacy encoding. A guess the
+    /// bytes don't make sense in is passed over, saving must give back the same bytes.
+    pub fn detect(bytes: &[u8]) -> (String, Self) {
+        if let Some((encoding, bom_length)) = Encoding::for_bom(bytes)
+            && let Some(text) = decode_exactly(encoding, &bytes[bom_length..])
+        {
+            return (
+                text,
+                Self {
+                    encoding,
+                    bom: true,
+                },
+            );
+        }
+
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return (text.to_string(), Self::UTF_8);
+        }
+
+        let legacy = || {
+            let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+            detector.feed(bytes, true);
+            detector.guess(None, Utf8Detection::Deny)
+        };
+
+        // windows-1252 makes something of any byte, the last resort
+        let (text, encoding) = guess_utf16(bytes)
+            .into_iter()
+            .chain(std::iter::once_with(legacy))
+            .chain([WINDOWS_1252])
+            .find_map(|encoding| Some((decode_exactly(encoding, bytes)?, encoding)))
+            .unwrap_or_else(|| {
+                let (text, _) = WINDOWS_1252.decode_without_bom_handling(bytes);
+                (text.into_owned(), WINDOWS_1252)
+            });
+
+        (
+            text,
+            Self {
+                encoding,
+                bom: false,
+            },
+        )
+    }
+
+    /// Decode `bytes` as this encoding, whether or not they start with its byte order mark.
+    pub fn decode(self, bytes: &[u8]) -> Result<(String, Self), Malformed> {
+        let (bom, rest) = match Encoding::for_bom(bytes) {
+            Some((encoding, bom_length)) if encoding == self.encoding => {
+                (true, &bytes[bom_length..])
+            }
+            _ => (false, bytes),
+        };
+
+        let text = self
+            .encoding
+            .decode_without_bom_handling_and_without_replacement(rest)
+            .ok_or(Malformed)?;
+
+        Ok((
+            text.into_owned(),
+            Self {
+                encoding: self.encoding,
+                bom,
+            },
+        ))
+    }
+
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, Unmappable> {
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+
+        // encoding_rs only encodes to UTF-16 by way of UTF-8, so do it by hand
+        if self.is_utf16() {
+            let to_bytes = if self.encoding == UTF_16LE {
+                u16::to_le_bytes
+            } else {
+                u16::to_be_bytes
+            };
+
+            if self.bom {
+                bytes.extend(to_bytes(0xFEFF));
+            }
+            bytes.extend(text.encode_utf16().flat_map(to_bytes));
+
+            return Ok(bytes);
+        }
+
+        if self.bom {
+            bytes.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+
+        let (encoded, _, unmappable) = self.encoding.encode(text);
+        if unmappable {
+            // encoding_rs would have written them as HTML character references
+            return Err(Unmappable);
+        }
+        bytes.extend_from_slice(&encoded);
+
+        Ok(bytes)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    fn is_utf16(&self) -> bool {
+        self.encoding == UTF_16LE || self.encoding == UTF_16BE
+    }
+}
+
+/// `bytes` as `encoding`, unless some of them don't make sense in it.
+fn decode_exactly(encoding: &'static Encoding, bytes: &[u8]) -> Option<String> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|text| text.into_owned())
+}
+
+/// Text files don't have NUL bytes, unless they are UTF-16.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    Encoding::for_bom(bytes).is_none() && guess_utf16(bytes).is_none() && bytes.contains(&0)
+}
+
+/// Text in UTF-16 without a byte order mark still gives itself away: mostly ASCII text leaves
+/// every other byte zero.
+fn guess_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let pairs = bytes.len() / 2;
+    let zeros_at = |offset: usize| {
+        bytes
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+
+    if odd * 10 >= pairs * 4 && even * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even * 10 >= pairs * 4 && odd * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Detect how `bytes` are encoded, then encode the text again that way.
+    fn round_trip(bytes: &[u8]) -> (Vec<u8>, TextEncoding) {
+        let (text, encoding) = TextEncoding::detect(bytes);
+        (encoding.encode(&text).unwrap(), encoding)
+    }
+
+    fn utf16(text: &str, to_bytes: fn(u16) -> [u8; 2], bom: bool) -> Vec<u8> {
+        let bom = bom.then_some(0xFEFF);
+        bom.into_iter()
+            .chain(text.encode_utf16())
+            .flat_map(to_bytes)
+            .collect()
+    }
+
+    const TEXT: &str = "Grüße, naïve café\nsecond line\n";
+
+    #[test]
+    fn utf8_with_and_without_bom() {
+        let plain = TEXT.as_bytes().to_vec();
+        assert_eq!(round_trip(&plain), (plain.clone(), TextEncoding::UTF_8));
+
+        let with_bom = [b"\xEF\xBB\xBF".as_slice(), &plain].concat();
+        let (bytes, encoding) = round_trip(&with_bom);
+        assert_eq!(bytes, with_bom);
+        assert_eq!(encoding.to_string(), "UTF-8 with BOM");
+    }
+
+    #[test]
+    fn utf16_with_and_without_bom() {
+        for (to_bytes, name) in [
+            (u16::to_le_bytes as fn(u16) -> [u8; 2], "UTF-16LE"),
+            (u16::to_be_bytes, "UTF-16BE"),
+        ] {
+            let with_bom = utf16(TEXT, to_bytes, true);
+            let (bytes, encoding) = round_trip(&with_bom);
+            assert_eq!(bytes, with_bom);
+            assert_eq!(encoding.to_string(), name);
+
+            let without_bom = utf16(TEXT, to_bytes, false);
+            let (bytes, encoding) = round_trip(&without_bom);
+            assert_eq!(bytes, without_bom);
+            assert_eq!(encoding.to_string(), format!("{} without BOM", name));
+        }
+    }
+
+    #[test]
+    fn windows_1252() {
+        let (bytes, _, _) = WINDOWS_1252.encode("Die Grüße, „café“ – 5 €\n");
+        let (round_tripped, encoding) = round_trip(&bytes);
+
+        assert_eq!(round_tripped, bytes.as_ref());
+        assert_eq!(encoding.name(), "windows-1252");
+    }
+
+    #[test]
+    fn malformed_guesses_are_passed_over() {
+        // a UTF-16 byte order mark, then a lone surrogate
+        let bytes = [0xFF, 0xFE, b'a', 0, 0x00, 0xD8, b'b', 0];
+
+        let (text, encoding) = TextEncoding::detect(&bytes);
+
+        assert!(!text.contains('\u{FFFD}'));
+        assert_eq!(encoding.encode(&text).unwrap(), bytes);
+    }
+
+    #[test]
+    fn any_byte_survives_the_last_resort() {
+        let bytes: Vec<u8> = (0..=255).collect();
+
+        assert_eq!(round_trip(&bytes).0, bytes);
+    }
+}
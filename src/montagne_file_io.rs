@@ -1,55 +1,918 @@
+use std::fmt;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
 
-// In any case we can show a msg to the user
+use iced::futures::{SinkExt, Stream};
+use similar::{DiffOp, TextDiff};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::montagne_backup::{BackupConfig, back_up};
+use crate::montagne_encoding::{TextEncoding, looks_binary};
+use crate::montagne_file_types::{FileTypesConfig, LargeFilesConfig};
+
+/// How much of a file is read at a time when loading it with progress.
+const CHUNK: usize = 1024 * 1024;
+
+/// How much of a file is looked at to tell whether it is binary.
+const SNIFF: usize = 8 * 1024;
+
+// In any case we can show a msg to the user, saying which file and what the OS said
 #[derive(Debug, Clone)]
 pub enum Error {
     DialogClosed,
-    IoError(io::ErrorKind),
+    /// Reading the file at `path` failed.
+    ReadFailed {
+        path: PathBuf,
+        source: Arc<io::Error>,
+    },
+    /// Saving to `path` stopped at `stage`. Before [`SaveStage::Replace`] the original file is
+    /// untouched.
+    SaveFailed {
+        path: PathBuf,
+        stage: SaveStage,
+        source: Arc<io::Error>,
+    },
+    /// Moving the file at `from` to `to` failed, `from` is still there.
+    MoveFailed {
+        from: PathBuf,
+        to: PathBuf,
+        source: Arc<io::Error>,
+    },
+    /// The file at `path` isn't valid text in the named encoding.
+    Decoding {
+        path: PathBuf,
+        encoding: &'static str,
+    },
+    /// The named encoding can't represent some of the text.
+    Encoding(&'static str),
+    /// The file at `path`, `len` bytes, doesn't fit in memory.
+    TooLarge {
+        path: PathBuf,
+        len: u64,
+    },
+    /// The file at the path is worth a second thought before opening it.
+    Unconfirmed(PathBuf, OpenWarning),
+}
+
+impl Error {
+    fn read(path: &Path) -> impl Fn(io::Error) -> Error + '_ {
+        move |source| Error::ReadFailed {
+            path: path.to_path_buf(),
+            source: Arc::new(source),
+        }
+    }
+
+    fn moving<'a>(from: &'a Path, to: &'a Path) -> impl Fn(io::Error) -> Error + 'a {
+        move |source| Error::MoveFailed {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            source: Arc::new(source),
+        }
+    }
+
+    /// The file the error is about.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::ReadFailed { path, .. }
+            | Error::SaveFailed { path, .. }
+            | Error::Decoding { path, .. }
+            | Error::TooLarge { path, .. }
+            | Error::Unconfirmed(path, _) => Some(path),
+            Error::MoveFailed { from, .. } => Some(from),
+            Error::DialogClosed | Error::Encoding(_) => None,
+        }
+    }
+
+    /// What the OS said went wrong, when it was the OS.
+    pub fn kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            Error::ReadFailed { source, .. }
+            | Error::SaveFailed { source, .. }
+            | Error::MoveFailed { source, .. } => Some(source.kind()),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.kind() == Some(io::ErrorKind::NotFound)
+    }
+
+    /// Trying again may work once the user sorted out what the OS complained about: permissions,
+    /// a full disk, a drive that wasn't mounted...
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_some()
+    }
+}
+
+/// Why a file is worth a second thought before opening it.
+#[derive(Debug, Clone, Copy)]
+pub enum OpenWarning {
+    /// Bigger than the configured limit, in bytes.
+    Large(u64),
+    /// Has bytes no text has.
+    Binary,
+}
+
+impl fmt::Display for OpenWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenWarning::Large(len) => write!(f, "is {:.1} MB", *len as f64 / 1024.0 / 1024.0),
+            OpenWarning::Binary => write!(f, "looks like a binary file"),
+        }
+    }
+}
+
+/// Where a load with progress is at.
+#[derive(Debug, Clone)]
+pub enum Loading {
+    /// Share of the file read so far, from 0 to 1.
+    Progress(f32),
+    Done(Result<LoadedFile, Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DialogClosed => write!(f, "Dialogue closed"),
+            Error::ReadFailed { path, source } => {
+                write!(f, "Could not read {}: {}", path.display(), source)
+            }
+            Error::SaveFailed {
+                path,
+                stage: SaveStage::Replace,
+                source,
+            } => write!(
+                f,
+                "Could not save {} while {}: {}. Your changes are in a temporary file next to it",
+                path.display(),
+                SaveStage::Replace,
+                source
+            ),
+            Error::SaveFailed {
+                path,
+                stage,
+                source,
+            } => write!(
+                f,
+                "Could not save {} while {}: {}. The file on disk is untouched",
+                path.display(),
+                stage,
+                source
+            ),
+            Error::MoveFailed { to, source, .. }
+                if source.kind() == io::ErrorKind::AlreadyExists =>
+            {
+                write!(f, "{} already exists", to.display())
+            }
+            Error::MoveFailed { from, to, source } => write!(
+                f,
+                "Could not move {} to {}: {}",
+                from.display(),
+                to.display(),
+                source
+            ),
+            Error::Decoding { path, encoding } => {
+                write!(f, "{} isn't valid {}", path.display(), encoding)
+            }
+            Error::TooLarge { path, len } => write!(
+                f,
+                "{} is {:.1} MB, too large to fit in memory",
+                path.display(),
+                *len as f64 / 1024.0 / 1024.0
+            ),
+            Error::Encoding(encoding) => write!(
+                f,
+                "{} can't represent some of the text, save with another encoding",
+                encoding
+            ),
+            Error::Unconfirmed(path, warning) => write!(f, "{} {}", path.display(), warning),
+        }
+    }
+}
+
+/// The steps of an atomic save, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveStage {
+    /// Finding the real target and checking we may write to it.
+    Prepare,
+    /// Writing the temporary file.
+    Write,
+    /// Flushing the temporary file to the disk.
+    Sync,
+    /// Giving the temporary file the permissions of the original.
+    Permissions,
+    /// Keeping a copy of the version about to be replaced.
+    Backup,
+    /// Renaming the temporary file over the target.
+    Replace,
+}
+
+impl fmt::Display for SaveStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveStage::Prepare => write!(f, "preparing"),
+            SaveStage::Write => write!(f, "writing"),
+            SaveStage::Sync => write!(f, "flushing to disk"),
+            SaveStage::Permissions => write!(f, "copying permissions"),
+            SaveStage::Backup => write!(f, "backing up the previous version"),
+            SaveStage::Replace => write!(f, "replacing the file"),
+        }
+    }
+}
+
+/// What the file looked like on disk the last time we touched it.
+/// Used to tell our own writes apart from changes made by other programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    read_only: bool,
+}
+
+impl FileStamp {
+    pub async fn of(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        let metadata = tokio::fs::metadata(path).await.ok()?;
+
+        // Permission bits alone don't tell whether *we* may write (owner, group, ACLs...),
+        // so ask the OS by opening the file for writing without touching its contents.
+        let read_only = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .await
+            .is_err();
+
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            read_only,
+        })
+    }
+
+    /// Whether both stamps describe the same file contents, ignoring permission changes.
+    pub fn same_contents(&self, other: &Self) -> bool {
+        self.modified == other.modified && self.len == other.len
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+/// How the lines of a file end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, Unix and macOS.
+    #[default]
+    Lf,
+    /// `\r\n`, Windows.
+    CrLf,
+    /// `\r`, classic Mac OS.
+    Cr,
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineEnding::Lf => write!(f, "LF"),
+            LineEnding::CrLf => write!(f, "CRLF"),
+            LineEnding::Cr => write!(f, "CR"),
+        }
+    }
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 3] = [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr];
+
+    /// Turn every line ending of `text` into `\n`, the only one the editor knows. Returns the
+    /// line ending most lines used and, when some used another one, how each line ended.
+    pub fn normalize(text: &str) -> (String, LineEnding, Option<Vec<LineEnding>>) {
+        let mut normalized = String::with_capacity(text.len());
+        let mut endings = Vec::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    endings.push(LineEnding::CrLf);
+                    normalized.push('\n');
+                }
+                '\r' => {
+                    endings.push(LineEnding::Cr);
+                    normalized.push('\n');
+                }
+                '\n' => {
+                    endings.push(LineEnding::Lf);
+                    normalized.push('\n');
+                }
+                c => normalized.push(c),
+            }
+        }
+
+        let counts = LineEnding::ALL.map(|line_ending| {
+            endings
+                .iter()
+                .filter(|&&ending| ending == line_ending)
+                .count()
+        });
+
+        // ties go to the first one, so a file without any line break counts as LF
+        let (most, _) = counts
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|&(_, count)| count)
+            .unwrap_or((0, &0));
+        let is_mixed = counts.iter().filter(|&&count| count > 0).count() > 1;
+
+        (
+            normalized,
+            LineEnding::ALL[most],
+            is_mixed.then_some(endings),
+        )
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// Put this line ending in place of the `\n`s of `text`.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            LineEnding::CrLf => text.replace('\n', "\r\n"),
+            LineEnding::Cr => text.replace('\n', "\r"),
+        }
+    }
+}
+
+/// How each line of a text that mixes line endings ends, so that saving it after an edit leaves
+/// the lines nobody touched as they were.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixedLineEndings {
+    /// The text with `\n` line endings.
+    text: Arc<String>,
+    /// The line ending of each `\n` of `text`.
+    endings: Vec<LineEnding>,
+}
+
+impl MixedLineEndings {
+    /// How the lines of `text`, an edit of ours, end: the lines left alone as they did, the
+    /// others with `line_ending`. `None` once they all end with `line_ending`.
+    pub fn edited(&self, text: Arc<String>, line_ending: LineEnding) -> Option<Self> {
+        let mut endings = vec![line_ending; text.matches('\n').count()];
+
+        // a file too different to diff quickly gets new line endings where it'd take long
+        let diff = TextDiff::configure()
+            .timeout(Duration::from_secs(1))
+            .diff_lines(self.text.as_str(), text.as_str());
+
+        for op in diff.ops() {
+            if let DiffOp::Equal {
+                old_index,
+                new_index,
+                len,
+            } = *op
+            {
+                for line in 0..len {
+                    if let (Some(&old), Some(new)) = (
+                        self.endings.get(old_index + line),
+                        endings.get_mut(new_index + line),
+                    ) {
+                        *new = old;
+                    }
+                }
+            }
+        }
+
+        let is_kept = endings.iter().any(|&ending| ending != line_ending);
+
+        is_kept.then_some(Self { text, endings })
+    }
+
+    /// The text with each line ending put back in place of its `\n`.
+    pub fn apply(&self) -> String {
+        let mut text = String::with_capacity(self.text.len() + self.endings.len());
+        let mut endings = self.endings.iter();
+
+        for line in self.text.split_inclusive('\n') {
+            match line.strip_suffix('\n') {
+                Some(line) => {
+                    text.push_str(line);
+                    text.push_str(endings.next().copied().unwrap_or_default().as_str());
+                }
+                None => text.push_str(line),
+            }
+        }
+
+        text
+    }
+}
+
+/// A file read from disk, decoded to text.
+#[derive(Debug, Clone)]
+pub struct LoadedFile {
+    pub path: PathBuf,
+    /// The text with `\n` line endings, whatever the file uses.
+    pub text: Arc<String>,
+    pub stamp: Option<FileStamp>,
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+    /// How each line ends, when not all of them end the same way.
+    pub mixed_line_endings: Option<MixedLineEndings>,
 }
 
 // Asynchronous flow for opening a file picker and then calling load_file()
-pub async fn open_file() -> Result<(PathBuf, Arc<String>), Error> {
-    let picked_file = rfd::AsyncFileDialog::new()
-        .set_title("Open a markdown file...")
-        .add_filter("markdown", &["md"])
+pub async fn open_file(
+    file_types: FileTypesConfig,
+    large_files: LargeFilesConfig,
+) -> Result<LoadedFile, Error> {
+    let picked_file = file_types
+        .filters(rfd::AsyncFileDialog::new().set_title("Open a file..."))
         .pick_file()
         .await
         .ok_or(Error::DialogClosed)?;
 
-    load_file(picked_file).await
+    open_checked(picked_file.path().to_path_buf(), large_files).await
 }
 
-// Asynchronously load a file given its PathBuffer
-pub async fn load_file(path: impl Into<PathBuf>) -> Result<(PathBuf, Arc<String>), Error> {
-    let path = path.into();
+/// Load a file the user asked for, unless it is too large or binary: that is
+/// [`Error::Unconfirmed`] until they say to open it anyway with [`load_with_progress`].
+pub async fn open_checked(
+    path: PathBuf,
+    large_files: LargeFilesConfig,
+) -> Result<LoadedFile, Error> {
+    let len = tokio::fs::metadata(&path)
+        .await
+        .map_err(Error::read(&path))?
+        .len();
+
+    if len > large_files.warn_above() {
+        return Err(Error::Unconfirmed(path, OpenWarning::Large(len)));
+    }
 
-    let contents = tokio::fs::read_to_string(&path)
+    let mut file = tokio::fs::File::open(&path)
         .await
-        .map(Arc::new)
-        .map_err(|error| Error::IoError(error.kind()))?;
+        .map_err(Error::read(&path))?;
+
+    let mut sample = Vec::with_capacity(SNIFF);
+    (&mut file)
+        .take(SNIFF as u64)
+        .read_to_end(&mut sample)
+        .await
+        .map_err(Error::read(&path))?;
+
+    if looks_binary(&sample) {
+        return Err(Error::Unconfirmed(path, OpenWarning::Binary));
+    }
 
-    Ok((path, contents))
+    load_file(path).await
 }
 
-pub async fn save_file(path: Option<PathBuf>, contents: String) -> Result<PathBuf, Error> {
+/// Load a file in chunks, telling how far along it is, for the large files that take a while.
+pub fn load_with_progress(path: PathBuf) -> impl Stream<Item = Loading> {
+    iced::stream::channel(10, move |mut output| async move {
+        let result = async {
+            let (mut file, mut bytes, len) = open_for_reading(&path).await?;
+
+            loop {
+                let read = (&mut file)
+                    .take(CHUNK as u64)
+                    .read_to_end(&mut bytes)
+                    .await
+                    .map_err(Error::read(&path))?;
+
+                if read == 0 {
+                    break;
+                }
+
+                let progress = bytes.len() as f32 / len.max(1) as f32;
+                let _ = output.send(Loading::Progress(progress.min(1.0))).await;
+            }
+
+            Ok(bytes)
+        }
+        .await;
+
+        let loaded = match result {
+            Ok(bytes) => decode_file(path, &bytes, None).await,
+            Err(error) => Err(error),
+        };
+
+        let _ = output.send(Loading::Done(loaded)).await;
+    })
+}
+
+// Asynchronously load a file given its PathBuffer, working out its encoding
+pub async fn load_file(path: impl Into<PathBuf>) -> Result<LoadedFile, Error> {
+    read_file(path.into(), None).await
+}
+
+/// Load a file again, as text in `encoding` rather than the one we detected.
+pub async fn reopen_file(path: PathBuf, encoding: TextEncoding) -> Result<LoadedFile, Error> {
+    read_file(path, Some(encoding)).await
+}
+
+async fn read_file(path: PathBuf, encoding: Option<TextEncoding>) -> Result<LoadedFile, Error> {
+    let (mut file, mut bytes, _) = open_for_reading(&path).await?;
+
+    file.read_to_end(&mut bytes)
+        .await
+        .map_err(Error::read(&path))?;
+
+    decode_file(path, &bytes, encoding).await
+}
+
+/// Open the file at `path`, with room for all of it, or [`Error::TooLarge`] when there isn't.
+async fn open_for_reading(path: &Path) -> Result<(tokio::fs::File, Vec<u8>, u64), Error> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(Error::read(path))?;
+    let len = file.metadata().await.map_err(Error::read(path))?.len();
+
+    let mut bytes = Vec::new();
+    usize::try_from(len)
+        .ok()
+        .and_then(|len| bytes.try_reserve_exact(len).ok())
+        .ok_or_else(|| Error::TooLarge {
+            path: path.to_path_buf(),
+            len,
+        })?;
+
+    Ok((file, bytes, len))
+}
+
+async fn decode_file(
+    path: PathBuf,
+    bytes: &[u8],
+    encoding: Option<TextEncoding>,
+) -> Result<LoadedFile, Error> {
+    let (text, encoding) = match encoding {
+        Some(encoding) => encoding.decode(bytes).map_err(|_| Error::Decoding {
+            path: path.clone(),
+            encoding: encoding.name(),
+        })?,
+        None => TextEncoding::detect(bytes),
+    };
+
+    let (text, line_ending, endings) = LineEnding::normalize(&text);
+    let text = Arc::new(text);
+    let mixed_line_endings = endings.map(|endings| MixedLineEndings {
+        text: text.clone(),
+        endings,
+    });
+
+    let stamp = FileStamp::of(&path).await;
+
+    Ok(LoadedFile {
+        path,
+        text,
+        stamp,
+        encoding,
+        line_ending,
+        mixed_line_endings,
+    })
+}
+
+pub async fn save_file(
+    path: Option<PathBuf>,
+    contents: String,
+    encoding: TextEncoding,
+    line_ending: LineEnding,
+    // how the lines of `contents` end, when not all of them end with `line_ending`
+    mixed_line_endings: Option<MixedLineEndings>,
+    backup: BackupConfig,
+    file_types: FileTypesConfig,
+) -> Result<(PathBuf, Option<FileStamp>), Error> {
+    let contents = match mixed_line_endings {
+        Some(mixed_line_endings) => mixed_line_endings.apply(),
+        None => line_ending.apply(&contents),
+    };
+
+    // before the dialog, there is no point picking a path for text we can't write
+    let bytes = encoding
+        .encode(&contents)
+        .map_err(|_| Error::Encoding(encoding.name()))?;
+
     let path = if let Some(path) = path {
         path
     } else {
-        rfd::AsyncFileDialog::new()
-            .add_filter("markdown", &["md"])
-            .save_file()
+        pick_save_path("Save as...", None, &file_types).await?
+    };
+
+    write_atomically(&path, &bytes, &backup).await?;
+
+    let stamp = FileStamp::of(&path).await;
+
+    Ok((path, stamp))
+}
+
+/// Move the file at `from` to `to`, or to a path picked by the user when there is none.
+/// An existing file is only replaced when the user picked it, the dialog asked them already.
+pub async fn move_file(
+    from: PathBuf,
+    to: Option<PathBuf>,
+    file_types: FileTypesConfig,
+) -> Result<(PathBuf, Option<FileStamp>), Error> {
+    let to = match to {
+        Some(to) => {
+            if to != from && tokio::fs::try_exists(&to).await.unwrap_or(false) {
+                return Err(Error::moving(&from, &to)(
+                    io::ErrorKind::AlreadyExists.into(),
+                ));
+            }
+            to
+        }
+        None => pick_save_path("Move to...", Some(&from), &file_types).await?,
+    };
+
+    if to != from
+        && let Err(error) = tokio::fs::rename(&from, &to).await
+    {
+        if error.kind() != io::ErrorKind::CrossesDevices {
+            return Err(Error::moving(&from, &to)(error));
+        }
+
+        // another file system, a rename can't get there
+        tokio::fs::copy(&from, &to)
+            .await
+            .map_err(Error::moving(&from, &to))?;
+        tokio::fs::remove_file(&from)
             .await
-            .as_ref()
-            .map(rfd::FileHandle::path)
-            .map(Path::to_owned)
-            .ok_or(Error::DialogClosed)?
+            .map_err(Error::moving(&from, &to))?;
+    }
+
+    let stamp = FileStamp::of(&to).await;
+
+    Ok((to, stamp))
+}
+
+/// Ask the user where to save, starting from `suggested` when given.
+async fn pick_save_path(
+    title: &str,
+    suggested: Option<&Path>,
+    file_types: &FileTypesConfig,
+) -> Result<PathBuf, Error> {
+    let mut dialog = file_types.filters(rfd::AsyncFileDialog::new().set_title(title));
+
+    if let Some(folder) = suggested.and_then(Path::parent) {
+        dialog = dialog.set_directory(folder);
+    }
+    if let Some(name) = suggested.and_then(Path::file_name) {
+        dialog = dialog.set_file_name(name.to_string_lossy());
+    }
+
+    dialog
+        .save_file()
+        .await
+        .as_ref()
+        .map(rfd::FileHandle::path)
+        .map(Path::to_owned)
+        .ok_or(Error::DialogClosed)
+}
+
+/// Write `contents` to a temporary file next to the target, flush it and rename it over the
+/// target. A crash or a full disk midway leaves the original file as it was.
+///
+/// Symlinks are followed so the link itself stays in place, and an existing target keeps its
+/// permissions. The version being replaced is backed up as `backup` says.
+async fn write_atomically(
+    path: &Path,
+    contents: &[u8],
+    backup: &BackupConfig,
+) -> Result<(), Error> {
+    let failed = |stage: SaveStage| {
+        move |source: io::Error| Error::SaveFailed {
+            path: path.to_path_buf(),
+            stage,
+            source: Arc::new(source),
+        }
+    };
+
+    let target = match tokio::fs::canonicalize(path).await {
+        Ok(target) => target,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(error) => return Err(failed(SaveStage::Prepare)(error)),
+    };
+
+    let permissions = match tokio::fs::metadata(&target).await {
+        Ok(metadata) => {
+            // Renaming over a file only needs write access to the directory,
+            // don't let that sneak past a file we aren't allowed to write.
+            tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(&target)
+                .await
+                .map_err(failed(SaveStage::Prepare))?;
+
+            Some(metadata.permissions())
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => return Err(failed(SaveStage::Prepare)(error)),
     };
 
-    tokio::fs::write(&path, contents)
+    let (temp, mut file) = create_temp(&target)
+        .await
+        .map_err(failed(SaveStage::Write))?;
+
+    let result = async {
+        file.write_all(contents)
+            .await
+            .map_err(failed(SaveStage::Write))?;
+
+        file.sync_all().await.map_err(failed(SaveStage::Sync))?;
+
+        if let Some(permissions) = permissions {
+            tokio::fs::set_permissions(&temp, permissions)
+                .await
+                .map_err(failed(SaveStage::Permissions))?;
+
+            back_up(&target, backup)
+                .await
+                .map_err(failed(SaveStage::Backup))?;
+        }
+
+        Ok(())
+    }
+    .await;
+
+    if let Err(error) = result {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(error);
+    }
+
+    // keep the temporary file if this fails, it holds the only copy of the new contents
+    tokio::fs::rename(&temp, &target)
         .await
-        .map_err(|error| Error::IoError(error.kind()))?;
+        .map_err(failed(SaveStage::Replace))?;
+
+    // Make the rename itself durable. Not every platform or filesystem lets us sync a directory,
+    // and the file is already in place by now, so this is best effort.
+    if let Some(directory) = target.parent()
+        && let Ok(directory) = tokio::fs::File::open(directory).await
+    {
+        let _ = directory.sync_all().await;
+    }
+
+    Ok(())
+}
+
+/// Create a new hidden sibling of `target` to write to, on the same filesystem so that renaming
+/// it is atomic. Every attempt gets a name of its own: the temporary file of a failed save is
+/// kept, and one left by a crashed run may carry our process id.
+async fn create_temp(target: &Path) -> io::Result<(PathBuf, tokio::fs::File)> {
+    static ATTEMPT: AtomicU64 = AtomicU64::new(0);
+
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    loop {
+        let attempt = ATTEMPT.fetch_add(1, Ordering::Relaxed);
+        let temp = target.with_file_name(format!(
+            ".{}.{}-{}.montagne-tmp",
+            name,
+            std::process::id(),
+            attempt
+        ));
+
+        match tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+            .await
+        {
+            Ok(file) => return Ok((temp, file)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Load `text` and save it again without editing it.
+    fn round_trip(text: &str) -> String {
+        let (normalized, line_ending, endings) = LineEnding::normalize(text);
+
+        match endings {
+            Some(endings) => MixedLineEndings {
+                text: Arc::new(normalized),
+                endings,
+            }
+            .apply(),
+            None => line_ending.apply(&normalized),
+        }
+    }
+
+    fn mixed(text: &str) -> MixedLineEndings {
+        let (normalized, _, endings) = LineEnding::normalize(text);
+
+        MixedLineEndings {
+            text: Arc::new(normalized),
+            endings: endings.expect("the text mixes line endings"),
+        }
+    }
+
+    #[test]
+    fn detects_the_line_ending() {
+        assert_eq!(
+            LineEnding::normalize("a\nb\n"),
+            ("a\nb\n".to_string(), LineEnding::Lf, None)
+        );
+        assert_eq!(
+            LineEnding::normalize("a\r\nb\r\n"),
+            ("a\nb\n".to_string(), LineEnding::CrLf, None)
+        );
+        assert_eq!(
+            LineEnding::normalize("a\rb"),
+            ("a\nb".to_string(), LineEnding::Cr, None)
+        );
+        assert_eq!(
+            LineEnding::normalize("no line break"),
+            ("no line break".to_string(), LineEnding::Lf, None)
+        );
+    }
+
+    #[test]
+    fn detects_mixed_line_endings() {
+        let (normalized, line_ending, endings) = LineEnding::normalize("a\r\nb\nc\r\n");
+
+        assert_eq!(normalized, "a\nb\nc\n");
+        assert_eq!(line_ending, LineEnding::CrLf);
+        assert_eq!(
+            endings,
+            Some(vec![LineEnding::CrLf, LineEnding::Lf, LineEnding::CrLf])
+        );
+    }
+
+    #[test]
+    fn saving_unedited_text_gives_back_the_file() {
+        for text in [
+            "",
+            "a\nb\n",
+            "a\r\nb\r\n",
+            "a\r\nb",
+            "a\rb\r",
+            "a\r\nb\nc\r\n",
+            "lone\rcarriage return\r\nand\nno trailing newline",
+            "\r\n\n\r\r\n",
+        ] {
+            assert_eq!(round_trip(text), text, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn edited_lines_get_the_line_ending_and_the_others_keep_theirs() {
+        let file = mixed("a\r\nb\nc\r\n");
+
+        let edited = file
+            .edited(Arc::new("a\nB\nnew\nc\n".to_string()), LineEnding::Lf)
+            .unwrap();
+
+        assert_eq!(edited.apply(), "a\r\nB\nnew\nc\r\n");
+    }
+
+    #[test]
+    fn kept_lines_keep_their_line_ending_when_no_other_is_left() {
+        let file = mixed("a\nb\nc\r\n");
+
+        let edited = file
+            .edited(Arc::new("c\n".to_string()), LineEnding::Lf)
+            .unwrap();
+
+        assert_eq!(edited.apply(), "c\r\n");
+    }
+
+    #[test]
+    fn editing_keeps_a_missing_trailing_newline() {
+        let file = mixed("a\nb\r\nc");
+
+        let edited = file
+            .edited(Arc::new("a\nb\nd\nc".to_string()), LineEnding::Lf)
+            .unwrap();
+
+        assert_eq!(edited.apply(), "a\nb\r\nd\nc");
+    }
+
+    #[test]
+    fn lines_all_ending_with_the_line_ending_keep_nothing() {
+        let file = mixed("a\r\nb\n");
 
-    Ok(path)
+        assert!(
+            file.edited(Arc::new("a\nc\n".to_string()), LineEnding::CrLf)
+                .is_none()
+        );
+    }
 }
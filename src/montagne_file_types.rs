@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Extensions of the "All files" filter. The portal takes `*` for any name, but the zenity
+/// fallback turns each extension into `*.ext`, which asks for a dot and hides `Makefile`:
+/// it splits the patterns on spaces, so `* *` slips a bare `*` in.
+#[cfg(all(unix, not(target_os = "macos")))]
+const ANY_FILE: &[&str] = &["*", "* *"];
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+const ANY_FILE: &[&str] = &["*"];
+
+/// The `[files]` table of the config file: which extensions are markdown, and which are offered
+/// as text in the open and save dialogs. Any other file opens as plain text too.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FileTypesConfig {
+    pub markdown: Vec<String>,
+    pub text: Vec<String>,
+}
+
+impl Default for FileTypesConfig {
+    fn default() -> Self {
+        Self {
+            markdown: ["md", "markdown", "mdx"].map(String::from).to_vec(),
+            text: vec!["txt".to_string()],
+        }
+    }
+}
+
+impl FileTypesConfig {
+    /// Files with a preview, the others are edited as plain text.
+    pub fn is_markdown(&self, path: &Path) -> bool {
+        has_extension(&self.markdown, path)
+    }
+
+    pub fn is_text(&self, path: &Path) -> bool {
+        has_extension(&self.text, path)
+    }
+
+    /// Markdown first, then text, then anything at all.
+    pub fn filters(&self, dialog: rfd::AsyncFileDialog) -> rfd::AsyncFileDialog {
+        dialog
+            .add_filter("Markdown", &self.markdown)
+            .add_filter("Text", &self.text)
+            .add_filter("All files", ANY_FILE)
+    }
+
+    /// What the editor highlights the file at `path` as, markdown when it has none yet.
+    pub fn syntax(&self, path: Option<&Path>) -> String {
+        let Some(path) = path.filter(|path| !self.is_markdown(path)) else {
+            return "md".to_string();
+        };
+
+        // files like `Makefile` are known by their name
+        path.extension()
+            .or_else(|| path.file_name())
+            .map(|token| token.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// The `[large_files]` table of the config file, sizes in megabytes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LargeFilesConfig {
+    /// Ask before opening files bigger than this.
+    pub warn_above_mb: u64,
+    /// Files bigger than this are edited as plain text, without preview or highlighting.
+    pub plain_above_mb: u64,
+}
+
+impl Default for LargeFilesConfig {
+    fn default() -> Self {
+        Self {
+            warn_above_mb: 20,
+            plain_above_mb: 2,
+        }
+    }
+}
+
+impl LargeFilesConfig {
+    pub fn warn_above(&self) -> u64 {
+        self.warn_above_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn plain_above(&self) -> u64 {
+        self.plain_above_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+fn has_extension(extensions: &[String], path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(extension))
+        })
+}
@@ -0,0 +1,292 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use iced::Subscription;
+use tokio::sync::Notify;
+
+use crate::montagne_cli::Args;
+
+/// Overrides where the socket lives, handy to try out several instances side by side.
+const SOCKET_VARIABLE: &str = "MONTAGNE_SOCKET";
+
+/// Someone waiting for the documents they asked for to be closed.
+#[derive(Debug, Clone)]
+pub struct Waiter(Arc<Notify>);
+
+impl Waiter {
+    /// Tell them we are done with their documents.
+    pub fn release(&self) {
+        self.0.notify_one();
+    }
+}
+
+/// Hand `args` to the Montagne already running, if there is one. With `--wait` this blocks
+/// until that Montagne is done with the files.
+///
+/// Returns whether a running instance took them.
+pub fn forward(args: &Args) -> io::Result<bool> {
+    #[cfg(unix)]
+    {
+        match socket_path() {
+            Some(path) => forward_to(&path, args),
+            None => Ok(false),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = args;
+        Ok(false)
+    }
+}
+
+#[cfg(unix)]
+fn forward_to(path: &std::path::Path, args: &Args) -> io::Result<bool> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let Ok(mut stream) = UnixStream::connect(path) else {
+        return Ok(false);
+    };
+
+    let request = serde_json::to_string(args).map_err(io::Error::other)?;
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(b"\n")?;
+
+    let mut lines = BufReader::new(stream).lines();
+
+    match lines.next().transpose()?.as_deref() {
+        Some("ok") => {}
+        _ => return Err(io::Error::other("the running Montagne refused the files")),
+    }
+
+    if args.wait {
+        // `done`, or the end of the stream should the running Montagne exit first
+        let _ = lines.next();
+    }
+
+    Ok(true)
+}
+
+/// The socket of the running instance, while we are it. Later invocations hand it their
+/// command line instead of opening a window of their own: one JSON encoded [`Args`] line,
+/// answered with `ok` once the files are being opened and, with `--wait`, `done` once they are
+/// all closed.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    path: PathBuf,
+    #[cfg(unix)]
+    listener: Arc<std::os::unix::net::UnixListener>,
+}
+
+impl Instance {
+    /// Become the running instance. Fails when another one got there first, or the platform
+    /// has no Unix sockets, in which case we simply run on our own.
+    pub fn bind() -> Option<Instance> {
+        #[cfg(unix)]
+        {
+            Self::bind_at(socket_path()?)
+        }
+
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    #[cfg(unix)]
+    fn bind_at(path: PathBuf) -> Option<Instance> {
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        if path.exists() && UnixStream::connect(&path).is_err() {
+            // left behind by a Montagne that didn't exit cleanly
+            let _ = std::fs::remove_file(&path);
+        }
+
+        if let Some(folder) = path.parent() {
+            let _ = std::fs::create_dir_all(folder);
+        }
+
+        let listener = UnixListener::bind(&path)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .inspect_err(|error| {
+                eprintln!("Not listening on {}: {}", path.display(), error);
+            })
+            .ok()?;
+
+        Some(Instance {
+            path,
+            listener: Arc::new(listener),
+        })
+    }
+
+    /// Command lines handed over by later invocations.
+    pub fn listen(&self) -> Subscription<(Args, Option<Waiter>)> {
+        #[cfg(unix)]
+        {
+            let listener = self.listener.clone();
+
+            Subscription::run_with_id(
+                self.path.clone(),
+                iced::stream::channel(10, move |output| serve(listener, output)),
+            )
+        }
+
+        #[cfg(not(unix))]
+        {
+            Subscription::none()
+        }
+    }
+
+    /// Stop being the running instance, the next Montagne takes over.
+    pub fn remove(&self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+async fn serve(
+    listener: Arc<std::os::unix::net::UnixListener>,
+    output: iced::futures::channel::mpsc::Sender<(Args, Option<Waiter>)>,
+) {
+    let listener = match listener
+        .try_clone()
+        .and_then(tokio::net::UnixListener::from_std)
+    {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Not accepting other Montagne invocations: {}", error);
+            return;
+        }
+    };
+
+    while let Ok((stream, _)) = listener.accept().await {
+        // a client waiting on its files mustn't hold up the next one
+        tokio::spawn(answer(stream, output.clone()));
+    }
+}
+
+#[cfg(unix)]
+async fn answer(
+    stream: tokio::net::UnixStream,
+    mut output: iced::futures::channel::mpsc::Sender<(Args, Option<Waiter>)>,
+) {
+    use iced::futures::SinkExt;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (read, mut write) = stream.into_split();
+
+    let Ok(Some(line)) = BufReader::new(read).lines().next_line().await else {
+        return;
+    };
+
+    let Ok(args) = serde_json::from_str::<Args>(&line) else {
+        let _ = write.write_all(b"error\n").await;
+        return;
+    };
+
+    let waiter = args.wait.then(|| Waiter(Arc::new(Notify::new())));
+
+    if output.send((args, waiter.clone())).await.is_err() {
+        return;
+    }
+
+    let _ = write.write_all(b"ok\n").await;
+
+    if let Some(Waiter(done)) = waiter {
+        done.notified().await;
+        let _ = write.write_all(b"done\n").await;
+    }
+}
+
+fn socket_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(SOCKET_VARIABLE) {
+        return Some(PathBuf::from(path));
+    }
+
+    match dirs::runtime_dir() {
+        Some(folder) => Some(folder.join("montagne.sock")),
+        // the temporary folder is shared, keep users apart
+        None => std::env::var_os("USER").map(|user| {
+            std::env::temp_dir().join(format!("montagne-{}.sock", user.to_string_lossy()))
+        }),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::net::UnixListener;
+
+    use iced::futures::StreamExt;
+    use iced::futures::channel::mpsc;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Args {
+        Args::parse(args.iter().map(|arg| arg.to_string())).unwrap()
+    }
+
+    #[test]
+    fn later_invocations_hand_over_their_files() {
+        let path = std::env::temp_dir().join(format!("montagne-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(
+            !forward_to(&path, &args(&["notes.md"])).unwrap(),
+            "nobody runs yet"
+        );
+
+        // left behind by a Montagne that crashed
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let instance = Instance::bind_at(path.clone()).expect("the stale socket gets replaced");
+        assert!(
+            Instance::bind_at(path.clone()).is_none(),
+            "the running instance keeps its socket"
+        );
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let (output, mut requests) = mpsc::channel(10);
+            let server = tokio::spawn(serve(instance.listener.clone(), output));
+
+            let client = {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || forward_to(&path, &args(&["notes.md"])))
+            };
+
+            let (received, waiter) = requests.next().await.unwrap();
+            assert_eq!(
+                received.files[0].path,
+                std::path::absolute("notes.md").unwrap()
+            );
+            assert!(waiter.is_none());
+            assert!(client.await.unwrap().unwrap(), "answered with ok");
+
+            let client = {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || {
+                    forward_to(&path, &args(&["--wait", "commit.txt"]))
+                })
+            };
+
+            let (received, waiter) = requests.next().await.unwrap();
+            assert!(received.wait);
+            assert!(!client.is_finished(), "waits for its files to be closed");
+
+            waiter.expect("someone waits").release();
+            assert!(client.await.unwrap().unwrap(), "answered with done");
+
+            server.abort();
+        });
+
+        instance.remove();
+        assert!(!path.exists());
+    }
+}
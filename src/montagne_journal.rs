@@ -0,0 +1,250 @@
+use std::fs::{File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+use crate::montagne_encoding::TextEncoding;
+use crate::montagne_file_io::{FileStamp, LineEnding, load_file};
+
+/// How often unsaved buffers are written to the journal.
+pub const JOURNAL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Every journal has a lock file next to it, locked for as long as the Montagne writing the
+/// journal runs. One that can be locked was left behind by a crash.
+const LOCK_EXTENSION: &str = "lock";
+
+/// This process' journal, and its lock file kept locked until we exit.
+static OWN_JOURNAL: OnceLock<Option<(PathBuf, File)>> = OnceLock::new();
+
+/// Locks of the journals of crashed runs we are recovering, so no other Montagne takes them too.
+static TAKEN_JOURNALS: Mutex<Vec<File>> = Mutex::new(Vec::new());
+
+/// An unsaved buffer as it was at `saved_at` (seconds since the epoch).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// `None` for a buffer that was never saved.
+    pub path: Option<PathBuf>,
+    pub text: String,
+    pub saved_at: u64,
+}
+
+impl JournalEntry {
+    pub fn new(path: Option<PathBuf>, text: String) -> Self {
+        Self {
+            path,
+            text,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+/// A journal entry left behind by a Montagne that didn't exit cleanly.
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    /// The journal file the entry came from.
+    pub journal: PathBuf,
+    pub entry: JournalEntry,
+    /// The file as it is on disk now, to compare the entry against.
+    pub disk_text: Option<Arc<String>>,
+    pub disk_stamp: Option<FileStamp>,
+    /// How the file on disk is encoded, the recovered text gets saved the same way.
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+    /// Unified diff from the file on disk to the entry.
+    pub diff: Option<String>,
+}
+
+/// Replace this process' journal with `entries`, or remove it when there is nothing unsaved.
+pub async fn write_journal(entries: Vec<JournalEntry>) -> Result<(), String> {
+    let path = own_journal().ok_or("The recovery journal is unavailable")?;
+
+    if entries.is_empty() {
+        return match tokio::fs::remove_file(&path).await {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.to_string()),
+            _ => Ok(()),
+        };
+    }
+
+    let contents =
+        serde_json::to_string(&Journal { entries }).map_err(|error| error.to_string())?;
+
+    if let Some(folder) = path.parent() {
+        tokio::fs::create_dir_all(folder)
+            .await
+            .map_err(|error| error.to_string())?;
+    }
+
+    // write and rename so a crash mid-write can't take the previous journal with it
+    let temp = path.with_extension("json.tmp");
+
+    tokio::fs::write(&temp, contents)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    tokio::fs::rename(&temp, &path)
+        .await
+        .map_err(|error| error.to_string())
+}
+
+/// Forget this process' journal, used when exiting on purpose.
+pub fn forget_journal() {
+    if let Some(path) = own_journal() {
+        discard_journal(&path);
+    }
+}
+
+/// Collect what was left unsaved by previous runs that crashed.
+pub async fn recover() -> Vec<Recovery> {
+    let mut recoveries = Vec::new();
+
+    let Some(folder) = journal_folder() else {
+        return recoveries;
+    };
+
+    let Ok(mut files) = tokio::fs::read_dir(&folder).await else {
+        return recoveries;
+    };
+
+    // lock ours first, another Montagne starting now mustn't think it crashed
+    let own = own_journal();
+
+    while let Ok(Some(file)) = files.next_entry().await {
+        let journal = file.path();
+
+        if journal
+            .extension()
+            .is_none_or(|extension| extension != "json")
+            || own.as_ref() == Some(&journal)
+        {
+            continue;
+        }
+
+        match take_journal(&journal) {
+            Ok(Some(lock)) => TAKEN_JOURNALS
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .push(lock),
+            Ok(None) => continue,
+            Err(error) => {
+                eprintln!("Could not lock journal {}: {}", journal.display(), error);
+                continue;
+            }
+        }
+
+        let Ok(contents) = tokio::fs::read_to_string(&journal).await else {
+            continue;
+        };
+
+        let Ok(Journal { entries }) = serde_json::from_str(&contents) else {
+            eprintln!("Ignoring unreadable journal {}", journal.display());
+            continue;
+        };
+
+        for entry in entries {
+            let file = match &entry.path {
+                Some(path) => load_file(path.clone()).await.ok(),
+                None => None,
+            };
+
+            let (disk_text, disk_stamp, encoding, line_ending) = match file {
+                Some(file) => (Some(file.text), file.stamp, file.encoding, file.line_ending),
+                None => (None, None, TextEncoding::default(), LineEnding::default()),
+            };
+
+            let diff = disk_text.as_ref().map(|disk_text| {
+                TextDiff::from_lines(disk_text.as_str(), entry.text.as_str())
+                    .unified_diff()
+                    .context_radius(2)
+                    .header("on disk", "unsaved")
+                    .to_string()
+            });
+
+            recoveries.push(Recovery {
+                journal: journal.clone(),
+                entry,
+                disk_text,
+                disk_stamp,
+                encoding,
+                line_ending,
+                diff,
+            });
+        }
+    }
+
+    recoveries
+}
+
+/// Remove a journal of a previous run once all of its entries were dealt with.
+pub fn discard_journal(journal: &Path) {
+    let _ = std::fs::remove_file(journal);
+    let _ = std::fs::remove_file(journal.with_extension(LOCK_EXTENSION));
+}
+
+fn journal_folder() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|folder| folder.join("montagne").join("journal"))
+}
+
+/// This process' journal, locked the first time it is asked for. The name has the start time
+/// in it too, a crashed run with the same process id doesn't get its journal overwritten.
+fn own_journal() -> Option<PathBuf> {
+    OWN_JOURNAL
+        .get_or_init(|| {
+            let folder = journal_folder()?;
+            let started = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let path = folder.join(format!("{}-{}.json", std::process::id(), started));
+
+            let lock = std::fs::create_dir_all(&folder)
+                .and_then(|_| lock_file(&path))
+                .and_then(|lock| {
+                    lock.try_lock().map_err(|error| match error {
+                        TryLockError::Error(error) => error,
+                        TryLockError::WouldBlock => io::ErrorKind::WouldBlock.into(),
+                    })?;
+                    Ok(lock)
+                });
+
+            match lock {
+                Ok(lock) => Some((path, lock)),
+                Err(error) => {
+                    eprintln!("Could not lock the recovery journal: {}", error);
+                    None
+                }
+            }
+        })
+        .as_ref()
+        .map(|(path, _)| path.clone())
+}
+
+/// Lock the journal of another run. `None` when that Montagne is still running: it holds the lock.
+fn take_journal(journal: &Path) -> io::Result<Option<File>> {
+    let lock = lock_file(journal)?;
+
+    match lock.try_lock() {
+        Ok(()) => Ok(Some(lock)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(error)) => Err(error),
+    }
+}
+
+fn lock_file(journal: &Path) -> io::Result<File> {
+    File::options()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(journal.with_extension(LOCK_EXTENSION))
+}
@@ -0,0 +1,128 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use similar::{MergeResolution, TextMerge};
+
+use crate::montagne_file_io::FileStamp;
+
+/// Which version of a hunk ends up in the merged document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Disk,
+    Mine,
+}
+
+/// Who changed a hunk since the file was last loaded (or saved).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkKind {
+    Unchanged,
+    /// Only the version on disk changed.
+    Disk,
+    /// Only our buffer changed.
+    Mine,
+    /// Both made the very same change.
+    Both,
+    /// Both changed it, differently.
+    Conflict,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub base: String,
+    pub disk: String,
+    pub mine: String,
+    pub kind: HunkKind,
+    pub side: Side,
+}
+
+impl Hunk {
+    /// Whether the user gets to pick a side for this hunk.
+    pub fn is_choice(&self) -> bool {
+        matches!(
+            self.kind,
+            HunkKind::Disk | HunkKind::Mine | HunkKind::Conflict
+        )
+    }
+
+    fn chosen(&self) -> &str {
+        match self.side {
+            Side::Disk => &self.disk,
+            Side::Mine => &self.mine,
+        }
+    }
+}
+
+/// A line based three-way merge of the last loaded text (base), the file on disk and our buffer.
+#[derive(Debug, Clone)]
+pub struct Merge {
+    hunks: Vec<Hunk>,
+    /// The version on disk the merge was made against.
+    pub disk_text: Arc<String>,
+    pub disk_stamp: Option<FileStamp>,
+}
+
+fn range_text<'a>(line: impl Fn(usize) -> Option<&'a str>, range: Range<usize>) -> String {
+    range.filter_map(line).collect()
+}
+
+impl Merge {
+    pub fn new(
+        base: &str,
+        disk_text: Arc<String>,
+        disk_stamp: Option<FileStamp>,
+        mine: &str,
+    ) -> Self {
+        let merge = TextMerge::from_lines(base, mine, disk_text.as_str());
+
+        let hunks = merge
+            .regions()
+            .iter()
+            .map(|region| {
+                let (kind, side) = match region.resolution() {
+                    MergeResolution::Unchanged => (HunkKind::Unchanged, Side::Mine),
+                    MergeResolution::Ours => (HunkKind::Mine, Side::Mine),
+                    MergeResolution::Theirs => (HunkKind::Disk, Side::Disk),
+                    MergeResolution::Both => (HunkKind::Both, Side::Mine),
+                    // keep the user's edits unless told otherwise
+                    _ => (HunkKind::Conflict, Side::Mine),
+                };
+
+                Hunk {
+                    base: range_text(|i| merge.base_line(i), region.base_range()),
+                    disk: range_text(|i| merge.theirs_line(i), region.theirs_range()),
+                    mine: range_text(|i| merge.ours_line(i), region.ours_range()),
+                    kind,
+                    side,
+                }
+            })
+            .collect();
+
+        Self {
+            hunks,
+            disk_text,
+            disk_stamp,
+        }
+    }
+
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    pub fn choose(&mut self, index: usize, side: Side) {
+        if let Some(hunk) = self.hunks.get_mut(index).filter(|hunk| hunk.is_choice()) {
+            hunk.side = side;
+        }
+    }
+
+    pub fn conflict_count(&self) -> usize {
+        self.hunks
+            .iter()
+            .filter(|hunk| hunk.kind == HunkKind::Conflict)
+            .count()
+    }
+
+    /// The document as resolved so far.
+    pub fn text(&self) -> String {
+        self.hunks.iter().map(Hunk::chosen).collect()
+    }
+}
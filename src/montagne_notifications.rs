@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// How long a toast stays up, errors excepted: they stay until acknowledged.
+pub const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// Toasts shown at once, the newest ones.
+const TOAST_LIMIT: usize = 3;
+
+/// Notifications kept in the log, the oldest ones go first.
+const HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "Info"),
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: u64,
+    pub severity: Severity,
+    pub message: String,
+    pub time: SystemTime,
+    /// The user saw it and closed it, it is only in the log now.
+    pub is_acknowledged: bool,
+}
+
+impl Notification {
+    /// Shown as a toast: errors until acknowledged, the others for a few seconds.
+    pub fn is_toast(&self) -> bool {
+        !self.is_acknowledged
+            && (self.severity == Severity::Error
+                || self
+                    .time
+                    .elapsed()
+                    .is_ok_and(|elapsed| elapsed < TOAST_DURATION))
+    }
+}
+
+/// What happened lately, for the status bar, the toasts and the log.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    log: VecDeque<Notification>,
+    next_id: u64,
+}
+
+impl Notifications {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Severity::Info, message.into());
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(Severity::Warning, message.into());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Severity::Error, message.into());
+    }
+
+    /// Kept in the log only, the user was told some other way.
+    pub fn record(&mut self, severity: Severity, message: String) {
+        self.push(severity, message);
+
+        if let Some(latest) = self.log.back_mut() {
+            latest.is_acknowledged = true;
+        }
+    }
+
+    pub fn push(&mut self, severity: Severity, message: String) {
+        // the same thing again, like typing in a locked document, doesn't pile up
+        if let Some(latest) = self.log.back_mut()
+            && latest.severity == severity
+            && latest.message == message
+            && !latest.is_acknowledged
+        {
+            latest.time = SystemTime::now();
+            return;
+        }
+
+        self.log.push_back(Notification {
+            id: self.next_id,
+            severity,
+            message,
+            time: SystemTime::now(),
+            is_acknowledged: false,
+        });
+        self.next_id += 1;
+
+        if self.log.len() > HISTORY {
+            self.log.pop_front();
+        }
+    }
+
+    pub fn latest(&self) -> Option<&Notification> {
+        self.log.back()
+    }
+
+    /// Newest first.
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.log.iter().rev()
+    }
+
+    /// The toasts to show, oldest first so the newest ends up at the bottom.
+    pub fn toasts(&self) -> Vec<&Notification> {
+        let mut toasts: Vec<_> = self
+            .history()
+            .filter(|notification| notification.is_toast())
+            .take(TOAST_LIMIT)
+            .collect();
+
+        toasts.reverse();
+        toasts
+    }
+
+    /// A toast is waiting to go away by itself, the view has to be redrawn when it does.
+    pub fn is_expiring(&self) -> bool {
+        self.log
+            .iter()
+            .any(|notification| notification.is_toast() && notification.severity != Severity::Error)
+    }
+
+    /// Errors nobody acknowledged yet.
+    pub fn pending_errors(&self) -> usize {
+        self.log
+            .iter()
+            .filter(|notification| {
+                notification.severity == Severity::Error && !notification.is_acknowledged
+            })
+            .count()
+    }
+
+    pub fn acknowledge(&mut self, id: u64) {
+        if let Some(notification) = self
+            .log
+            .iter_mut()
+            .find(|notification| notification.id == id)
+        {
+            notification.is_acknowledged = true;
+        }
+    }
+
+    pub fn acknowledge_all(&mut self) {
+        for notification in &mut self.log {
+            notification.is_acknowledged = true;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.log.clear();
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Files remembered besides the pinned ones.
+const LIMIT: usize = 20;
+
+/// Files opened or saved lately, most recent first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    pub files: Vec<RecentFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    /// Stays at the top of the list, and in it however many files are opened after.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl RecentFiles {
+    /// Read before the window opens like the session, so it is blocking as well.
+    pub fn load() -> RecentFiles {
+        let Some(path) = recent_path() else {
+            return RecentFiles::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+                eprintln!(
+                    "Ignoring unreadable recent files {}: {}",
+                    path.display(),
+                    error
+                );
+                RecentFiles::default()
+            }),
+            Err(_) => RecentFiles::default(),
+        }
+    }
+
+    /// Written whenever the list changes, so another window started later sees it.
+    pub async fn save(self) -> Result<(), String> {
+        let path = recent_path().ok_or("no data directory to keep recent files in")?;
+
+        let contents = serde_json::to_string_pretty(&self).map_err(|error| error.to_string())?;
+
+        if let Some(folder) = path.parent() {
+            tokio::fs::create_dir_all(folder)
+                .await
+                .map_err(|error| error.to_string())?;
+        }
+
+        tokio::fs::write(&path, contents)
+            .await
+            .map_err(|error| error.to_string())
+    }
+
+    /// Put `path` at the top of the list, keeping whether it is pinned.
+    pub fn add(&mut self, path: &Path) {
+        let pinned = self.remove(path).is_some_and(|file| file.pinned);
+
+        self.files.insert(
+            0,
+            RecentFile {
+                path: path.to_path_buf(),
+                pinned,
+            },
+        );
+
+        let mut unpinned = 0;
+        self.files.retain(|file| {
+            unpinned += usize::from(!file.pinned);
+            file.pinned || unpinned <= LIMIT
+        });
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<RecentFile> {
+        let index = self.files.iter().position(|file| file.path == path)?;
+
+        Some(self.files.remove(index))
+    }
+
+    /// The file at `from` is now at `to`, it keeps its place in the list.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        self.files.retain(|file| file.path != to);
+
+        match self.files.iter_mut().find(|file| file.path == from) {
+            Some(file) => file.path = to.to_path_buf(),
+            None => self.add(to),
+        }
+    }
+
+    pub fn toggle_pin(&mut self, path: &Path) {
+        if let Some(file) = self.files.iter_mut().find(|file| file.path == path) {
+            file.pinned = !file.pinned;
+        }
+    }
+
+    /// Pinned files first, then the others, keeping only those whose path contains `filter`
+    /// (ignoring case).
+    pub fn matching(&self, filter: &str) -> Vec<&RecentFile> {
+        let filter = filter.to_lowercase();
+
+        let (mut pinned, unpinned): (Vec<_>, Vec<_>) = self
+            .files
+            .iter()
+            .filter(|file| file.path.to_string_lossy().to_lowercase().contains(&filter))
+            .partition(|file| file.pinned);
+
+        pinned.extend(unpinned);
+        pinned
+    }
+
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|file| file.path.clone()).collect()
+    }
+}
+
+/// Those of `paths` that no longer exist.
+pub async fn find_missing(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut missing = Vec::new();
+
+    for path in paths {
+        // a file we can't even check for is as good as gone
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            missing.push(path);
+        }
+    }
+
+    missing
+}
+
+fn recent_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|folder| folder.join("montagne").join("recent.json"))
+}
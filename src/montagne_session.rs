@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use iced::{Point, Size, window};
+use serde::{Deserialize, Serialize};
+
+use crate::Mode;
+
+/// What was open when Montagne last exited, restored on the next start.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub documents: Vec<DocumentSession>,
+    /// Index of the active document in `documents`.
+    pub active: usize,
+    pub window: Option<WindowGeometry>,
+    /// Folder open in the sidebar.
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSession {
+    pub path: PathBuf,
+    /// Line and column (in characters) of the cursor.
+    pub cursor: (usize, usize),
+    pub mode: Mode,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    /// Not every platform tells (or lets us set) where a window is.
+    pub position: Option<(f32, f32)>,
+}
+
+impl WindowGeometry {
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    pub fn position(&self) -> window::Position {
+        match self.position {
+            Some((x, y)) => window::Position::Specific(Point::new(x, y)),
+            None => window::Position::Centered,
+        }
+    }
+}
+
+impl Session {
+    /// Read the last session. Runs before the window opens, so it is blocking on purpose.
+    /// A missing or unreadable session simply starts a fresh one.
+    pub fn load() -> Session {
+        let Some(path) = session_path() else {
+            return Session::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+                eprintln!("Ignoring unreadable session {}: {}", path.display(), error);
+                Session::default()
+            }),
+            Err(_) => Session::default(),
+        }
+    }
+
+    /// Written when exiting, so it is blocking as well.
+    pub fn save(&self) {
+        let Some(path) = session_path() else {
+            return;
+        };
+
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                if let Some(folder) = path.parent() {
+                    std::fs::create_dir_all(folder).map_err(|error| error.to_string())?;
+                }
+                std::fs::write(&path, contents).map_err(|error| error.to_string())
+            });
+
+        if let Err(error) = result {
+            eprintln!("Could not save session to {}: {}", path.display(), error);
+        }
+    }
+}
+
+fn session_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|folder| folder.join("montagne").join("session.json"))
+}
@@ -0,0 +1,53 @@
+use iced::keyboard::{Key, Modifiers, key::Named};
+use iced::widget::text_editor::{Binding, KeyPress, Status};
+
+use crate::message::Message;
+
+/// Application wide keyboard shortcuts.
+pub fn shortcut(key: &Key, modifiers: Modifiers) -> Option<Message> {
+    if !modifiers.command() {
+        return None;
+    }
+
+    match key.as_ref() {
+        Key::Character("b") => Some(Message::ToggleSidebar),
+        Key::Character("n") => Some(Message::NewFile),
+        Key::Character("o") => Some(Message::OpenFile),
+        Key::Character("r") => Some(Message::ShowRecent),
+        Key::Character("s" | "S") if modifiers.shift() => Some(Message::SaveFileAs),
+        Key::Character("s") => Some(Message::SaveFile),
+        Key::Character("w") => Some(Message::CloseActiveTab),
+        Key::Character("z" | "Z") if modifiers.shift() => Some(Message::Redo),
+        Key::Character("z") => Some(Message::Undo),
+        Key::Character("y") => Some(Message::Redo),
+        Key::Named(Named::Tab) if modifiers.shift() => Some(Message::PreviousTab),
+        Key::Named(Named::Tab) => Some(Message::NextTab),
+        Key::Named(Named::PageUp) if modifiers.shift() => Some(Message::MoveTab(-1)),
+        Key::Named(Named::PageDown) if modifiers.shift() => Some(Message::MoveTab(1)),
+        Key::Named(Named::PageUp) => Some(Message::PreviousTab),
+        Key::Named(Named::PageDown) => Some(Message::NextTab),
+        _ => None,
+    }
+}
+
+/// Keys of the recent files picker, while it is open. The arrows get here while its filter is
+/// focused, Escape once it is not.
+pub fn picker_key(key: Key, _modifiers: Modifiers) -> Option<Message> {
+    match key.as_ref() {
+        Key::Named(Named::ArrowUp) => Some(Message::MoveRecentSelection(-1)),
+        Key::Named(Named::ArrowDown) => Some(Message::MoveRecentSelection(1)),
+        Key::Named(Named::Escape) => Some(Message::CloseRecent),
+        _ => None,
+    }
+}
+
+/// The focused editor sees key presses first, let the shortcuts through before its own bindings.
+pub fn editor_key_binding(key_press: KeyPress) -> Option<Binding<Message>> {
+    if key_press.status == Status::Focused
+        && let Some(message) = shortcut(&key_press.key, key_press.modifiers)
+    {
+        return Some(Binding::Custom(message));
+    }
+
+    Binding::from_key_press(key_press)
+}
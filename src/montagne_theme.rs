@@ -1,6 +1,8 @@
 use iced::widget::{container, scrollable, text};
 use iced::{Border, Color, Element, Font, Shadow, Theme};
 
+use crate::montagne_merge::HunkKind;
+
 // styling
 pub fn editor_style(_theme: &Theme) -> container::Style {
     container::Style {
@@ -95,6 +97,33 @@ pub fn preview_scrollable_style(_theme: &Theme, status: scrollable::Status) -> s
     }
 }
 
+pub fn hunk_style(kind: HunkKind) -> impl Fn(&Theme) -> container::Style {
+    move |_theme| {
+        let color = match kind {
+            HunkKind::Unchanged => Color::TRANSPARENT,
+            HunkKind::Disk => Color::from_rgba(0.30, 0.50, 0.90, 0.25),
+            HunkKind::Mine => Color::from_rgba(0.30, 0.80, 0.40, 0.25),
+            HunkKind::Both => Color::from_rgba(0.60, 0.60, 0.60, 0.25),
+            HunkKind::Conflict => Color::from_rgba(0.90, 0.30, 0.30, 0.35),
+        };
+
+        container::Style {
+            background: Some(iced::Background::Color(color)),
+            ..container::Style::default()
+        }
+    }
+}
+
+/// Colour of a line of a unified diff, by its prefix.
+pub fn diff_line_color(line: &str) -> Color {
+    match line.chars().next() {
+        Some('+') => Color::from_rgb(0.45, 0.85, 0.50),
+        Some('-') => Color::from_rgb(0.95, 0.45, 0.45),
+        Some('@') => Color::from_rgb(0.50, 0.65, 0.95),
+        _ => Color::WHITE,
+    }
+}
+
 // directly copied from https://github.com/iced-rs/iced/blob/9bfbd7cda79aceef2d115b8bb35e8f3257dcabf2/examples/editor/src/main.rs#L306
 
 pub fn new_icon<'a, Message>() -> Element<'a, Message> {
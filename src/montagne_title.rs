@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Parts of the title separated by this are left out when they come out empty, like the folder
+/// of a file never saved.
+const SEPARATOR: &str = " — ";
+
+/// The `[title]` table of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TitleConfig {
+    /// The window title, with `{dirty}`, `{name}`, `{folder}`, `{path}`, `{workspace}` and
+    /// `{mode}` filled in for the active document.
+    pub format: String,
+}
+
+impl Default for TitleConfig {
+    fn default() -> Self {
+        Self {
+            format: "{dirty}{name} — {folder} — Montagne [{mode}]".to_string(),
+        }
+    }
+}
+
+/// What the title can show about the active document.
+pub struct TitleParts<'a> {
+    pub name: String,
+    pub path: Option<&'a Path>,
+    pub workspace: Option<String>,
+    pub mode: String,
+    pub is_dirty: bool,
+}
+
+impl TitleConfig {
+    pub fn title(&self, parts: TitleParts) -> String {
+        let folder = parts
+            .path
+            .and_then(Path::parent)
+            .map(shorten_home)
+            .unwrap_or_default();
+        let path = parts.path.map(shorten_home).unwrap_or_default();
+
+        let title = self
+            .format
+            .replace("{dirty}", if parts.is_dirty { "• " } else { "" })
+            .replace("{name}", &parts.name)
+            .replace("{folder}", &folder)
+            .replace("{path}", &path)
+            .replace(
+                "{workspace}",
+                parts.workspace.as_deref().unwrap_or_default(),
+            )
+            .replace("{mode}", &parts.mode);
+
+        title
+            .split(SEPARATOR)
+            .filter(|part| !part.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join(SEPARATOR)
+    }
+}
+
+/// `~/docs` rather than `/home/someone/docs`.
+fn shorten_home(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display()),
+        None => path.display().to_string(),
+    }
+}
@@ -0,0 +1,404 @@
+use std::time::{Duration, Instant};
+
+use iced::widget::text_editor::{self, Action, Motion};
+
+use crate::{cursor_position, move_cursor};
+
+/// A pause in typing longer than this starts a new undo step.
+const GROUP_PAUSE: Duration = Duration::from_secs(1);
+
+/// Undo steps kept per document, the oldest go first.
+const LIMIT: usize = 500;
+
+/// Selections longer than this (in characters) aren't selected again on undo, it would take
+/// a key press worth of work per character.
+const RESELECT_LIMIT: usize = 10_000;
+
+/// Consecutive edits of the same kind undo together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    /// Characters typed one after the other.
+    Typing,
+    /// Backspace or Delete pressed again and again.
+    Deleting,
+    /// A paste, a line break or the whole text replaced: an undo step of its own.
+    Single,
+}
+
+impl EditKind {
+    pub fn of(edit: &text_editor::Edit) -> Self {
+        match edit {
+            text_editor::Edit::Insert(_) => EditKind::Typing,
+            text_editor::Edit::Backspace | text_editor::Edit::Delete => EditKind::Deleting,
+            text_editor::Edit::Paste(_) | text_editor::Edit::Enter => EditKind::Single,
+        }
+    }
+}
+
+/// Where the cursor was, and what was selected.
+#[derive(Debug, Clone, Default)]
+pub struct Cursor {
+    /// Line and column, in characters.
+    pub position: (usize, usize),
+    pub selection: Option<String>,
+}
+
+impl Cursor {
+    pub fn of(content: &text_editor::Content) -> Self {
+        Self {
+            position: cursor_position(content),
+            selection: content.selection(),
+        }
+    }
+
+    /// Put the cursor of `content` back here, selecting what was selected.
+    pub fn restore(&self, content: &mut text_editor::Content) {
+        move_cursor(content, self.position);
+
+        let Some(selection) = &self.selection else {
+            return;
+        };
+        let length = selection.chars().count();
+        if length > RESELECT_LIMIT {
+            return;
+        }
+
+        // we know where the cursor was, not which way the selection went from it
+        for motion in [Motion::Left, Motion::Right] {
+            for _ in 0..length {
+                content.perform(Action::Select(motion));
+            }
+
+            if content.selection().as_ref() == Some(selection) {
+                return;
+            }
+            move_cursor(content, self.position);
+        }
+    }
+}
+
+/// One undo step: at byte `start` of the text, `removed` was replaced by `inserted`.
+#[derive(Debug, Clone)]
+struct Change {
+    start: usize,
+    removed: String,
+    inserted: String,
+    before: Cursor,
+    after: Cursor,
+}
+
+/// The edits of the step being made.
+#[derive(Debug, Clone)]
+struct Group {
+    kind: EditKind,
+    before: Cursor,
+    after: Cursor,
+    last_edit: Instant,
+    /// The cursor moved since, the next edit starts a new step.
+    is_closed: bool,
+}
+
+/// The undo and redo steps of a document. The step being made is only worked out once it ends,
+/// so typing doesn't compare the whole text on every key.
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+    /// The text before the step being made, or the current text when there is none.
+    snapshot: String,
+    group: Option<Group>,
+}
+
+impl UndoHistory {
+    /// Forget every step, the buffer now holds `text`.
+    pub fn reset(&mut self, text: String) {
+        *self = Self {
+            snapshot: text,
+            ..Self::default()
+        };
+    }
+
+    /// An edit of `kind` is about to be made, the cursor at `cursor`. `text` is the buffer as
+    /// it is now, only asked for when the edits before end their step.
+    pub fn before_edit(&mut self, kind: EditKind, cursor: Cursor, text: impl FnOnce() -> String) {
+        let continues = cursor.selection.is_none()
+            && self.group.as_ref().is_some_and(|group| {
+                !group.is_closed
+                    && group.kind == kind
+                    && kind != EditKind::Single
+                    && group.last_edit.elapsed() < GROUP_PAUSE
+            });
+
+        if !continues {
+            self.commit(text);
+            self.group = Some(Group {
+                kind,
+                before: cursor.clone(),
+                after: cursor,
+                last_edit: Instant::now(),
+                is_closed: false,
+            });
+        }
+
+        self.redo.clear();
+    }
+
+    /// The edit was made, leaving the cursor at `cursor`.
+    pub fn after_edit(&mut self, cursor: Cursor) {
+        if let Some(group) = &mut self.group {
+            group.after = cursor;
+            group.last_edit = Instant::now();
+        }
+    }
+
+    /// The cursor moved, the next edit starts a new step.
+    pub fn break_group(&mut self) {
+        if let Some(group) = &mut self.group {
+            group.is_closed = true;
+        }
+    }
+
+    /// Undo the last step. `text` is the buffer as it is now. Gives the text to show and where
+    /// the cursor goes, or `None` when there is nothing to undo.
+    pub fn undo(&mut self, text: impl FnOnce() -> String) -> Option<(String, Cursor)> {
+        self.commit(text);
+
+        let change = self.undo.pop()?;
+        self.snapshot.replace_range(
+            change.start..change.start + change.inserted.len(),
+            &change.removed,
+        );
+
+        let cursor = change.before.clone();
+        self.redo.push(change);
+
+        Some((self.snapshot.clone(), cursor))
+    }
+
+    /// Redo the last step undone, like [`UndoHistory::undo`].
+    pub fn redo(&mut self, text: impl FnOnce() -> String) -> Option<(String, Cursor)> {
+        if self.redo.is_empty() {
+            return None;
+        }
+        // the step being made, if any, came after the undo and already emptied `redo`
+        self.commit(text);
+
+        let change = self.redo.pop()?;
+        self.snapshot.replace_range(
+            change.start..change.start + change.removed.len(),
+            &change.inserted,
+        );
+
+        let cursor = change.after.clone();
+        self.undo.push(change);
+
+        Some((self.snapshot.clone(), cursor))
+    }
+
+    /// End the step being made, the buffer being `text` now.
+    fn commit(&mut self, text: impl FnOnce() -> String) {
+        let Some(group) = self.group.take() else {
+            return;
+        };
+
+        let text = text();
+
+        if let Some(change) = diff(&self.snapshot, &text, group.before, group.after) {
+            self.undo.push(change);
+
+            if self.undo.len() > LIMIT {
+                self.undo.remove(0);
+            }
+        }
+
+        self.snapshot = text;
+    }
+}
+
+/// What changed from `before` to `after`: the part between what they start and end with alike.
+fn diff(before: &str, after: &str, cursor_before: Cursor, cursor_after: Cursor) -> Option<Change> {
+    if before == after {
+        return None;
+    }
+
+    let mut start = before
+        .bytes()
+        .zip(after.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(start) || !after.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let longest = before.len().min(after.len()) - start;
+    let mut end = before
+        .bytes()
+        .rev()
+        .zip(after.bytes().rev())
+        .take(longest)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(before.len() - end) || !after.is_char_boundary(after.len() - end)
+    {
+        end -= 1;
+    }
+
+    Some(Change {
+        start,
+        removed: before[start..before.len() - end].to_string(),
+        inserted: after[start..after.len() - end].to_string(),
+        before: cursor_before,
+        after: cursor_after,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: usize, column: usize) -> Cursor {
+        Cursor {
+            position: (line, column),
+            selection: None,
+        }
+    }
+
+    /// A buffer kept the way the editor keeps it, telling the history about each edit.
+    struct Buffer {
+        text: String,
+        history: UndoHistory,
+    }
+
+    impl Buffer {
+        fn new(text: &str) -> Self {
+            let mut history = UndoHistory::default();
+            history.reset(text.to_string());
+
+            Self {
+                text: text.to_string(),
+                history,
+            }
+        }
+
+        fn edit(&mut self, kind: EditKind, after: &str) {
+            let text = &self.text;
+            self.history
+                .before_edit(kind, at(0, text.chars().count()), || text.clone());
+
+            self.text = after.to_string();
+            self.history.after_edit(at(0, after.chars().count()));
+        }
+
+        fn undo(&mut self) -> Option<Cursor> {
+            let text = &self.text;
+            let (text, cursor) = self.history.undo(|| text.clone())?;
+
+            self.text = text;
+            Some(cursor)
+        }
+
+        fn redo(&mut self) -> Option<Cursor> {
+            let text = &self.text;
+            let (text, cursor) = self.history.redo(|| text.clone())?;
+
+            self.text = text;
+            Some(cursor)
+        }
+    }
+
+    #[test]
+    fn diff_finds_the_changed_middle() {
+        let change = diff("abc", "abXc", at(0, 2), at(0, 3)).unwrap();
+
+        assert_eq!(change.start, 2);
+        assert_eq!(change.removed, "");
+        assert_eq!(change.inserted, "X");
+    }
+
+    #[test]
+    fn diff_of_the_same_text_is_nothing() {
+        assert!(diff("same", "same", at(0, 0), at(0, 0)).is_none());
+    }
+
+    #[test]
+    fn diff_keeps_to_char_boundaries() {
+        // é and è share their first byte, ü and ö their last
+        let change = diff("aéb", "aèb", at(0, 1), at(0, 2)).unwrap();
+        assert_eq!(
+            (change.removed.as_str(), change.inserted.as_str()),
+            ("é", "è")
+        );
+
+        let change = diff("üx", "öx", at(0, 0), at(0, 1)).unwrap();
+        assert_eq!(
+            (change.removed.as_str(), change.inserted.as_str()),
+            ("ü", "ö")
+        );
+    }
+
+    #[test]
+    fn typing_run_undoes_as_one_step() {
+        let mut buffer = Buffer::new("");
+
+        buffer.edit(EditKind::Typing, "a");
+        buffer.edit(EditKind::Typing, "ab");
+
+        let cursor = buffer.undo().unwrap();
+        assert_eq!(buffer.text, "");
+        assert_eq!(cursor.position, (0, 0));
+        assert!(buffer.undo().is_none());
+    }
+
+    #[test]
+    fn moving_the_cursor_or_another_kind_starts_a_step() {
+        let mut buffer = Buffer::new("");
+
+        buffer.edit(EditKind::Typing, "a");
+        buffer.edit(EditKind::Typing, "ab");
+        buffer.history.break_group();
+        buffer.edit(EditKind::Typing, "abc");
+        buffer.edit(EditKind::Deleting, "ab");
+
+        buffer.undo().unwrap();
+        assert_eq!(buffer.text, "abc");
+        buffer.undo().unwrap();
+        assert_eq!(buffer.text, "ab");
+        buffer.undo().unwrap();
+        assert_eq!(buffer.text, "");
+    }
+
+    #[test]
+    fn paste_is_a_step_of_its_own() {
+        let mut buffer = Buffer::new("");
+
+        buffer.edit(EditKind::Single, "one");
+        buffer.edit(EditKind::Single, "one two");
+
+        buffer.undo().unwrap();
+        assert_eq!(buffer.text, "one");
+        buffer.undo().unwrap();
+        assert_eq!(buffer.text, "");
+    }
+
+    #[test]
+    fn redo_after_undo_and_a_new_edit_forgets_it() {
+        let mut buffer = Buffer::new("naïve");
+
+        buffer.edit(EditKind::Typing, "naïve ");
+        buffer.edit(EditKind::Typing, "naïve café");
+
+        buffer.undo().unwrap();
+        assert_eq!(buffer.text, "naïve");
+
+        let cursor = buffer.redo().unwrap();
+        assert_eq!(buffer.text, "naïve café");
+        assert_eq!(cursor.position, (0, 10));
+
+        buffer.undo().unwrap();
+        buffer.edit(EditKind::Typing, "naïve!");
+        assert!(buffer.redo().is_none());
+
+        buffer.undo().unwrap();
+        assert_eq!(buffer.text, "naïve");
+    }
+}
@@ -0,0 +1,174 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tokio::time::{Instant, timeout_at};
+
+use iced::{
+    Subscription,
+    futures::{SinkExt, StreamExt, channel::mpsc},
+    stream,
+};
+use notify::{
+    EventKind, RecursiveMode, Watcher,
+    event::{ModifyKind, RenameMode},
+};
+
+use crate::montagne_file_io::FileStamp;
+
+/// Tools like git or other editors often write a file in several steps (truncate, write, rename).
+/// We wait for the file to stay quiet this long before reporting a change.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// A file written to all the time, like a log, still gets reported this often.
+const MAX_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// What happened to the watched file once a burst of events has settled.
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// The file still exists, with new contents or permissions.
+    Modified(FileStamp),
+    Deleted,
+    /// The file was moved to another name in the same directory.
+    Renamed(PathBuf),
+}
+
+/// Watch `path` on disk and report whenever another program changes it.
+///
+/// The subscription is keyed on the path, so it is restarted whenever a document's path changes.
+/// Filtering out our own writes is left to the caller, who knows the stamp of the last save.
+pub fn watch(path: PathBuf) -> Subscription<Change> {
+    Subscription::run_with_id(
+        path.clone(),
+        stream::channel(10, move |output| watch_file(path, output)),
+    )
+}
+
+async fn watch_file(path: PathBuf, mut output: mpsc::Sender<Change>) {
+    let Some(file_name) = path.file_name().map(OsString::from) else {
+        return;
+    };
+
+    // Watch the parent directory instead of the file itself: most editors save by
+    // replacing the file, which would silently drop a watch placed on the old inode.
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let (sender, mut events) = mpsc::unbounded();
+
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = sender.unbounded_send(event);
+        }
+    });
+
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            eprintln!("Could not watch {}: {}", path.display(), error);
+            return;
+        }
+    };
+
+    if let Err(error) = watcher.watch(&directory, RecursiveMode::NonRecursive) {
+        eprintln!("Could not watch {}: {}", directory.display(), error);
+        return;
+    }
+
+    let is_file = |changed: &PathBuf| changed.file_name() == Some(file_name.as_os_str());
+
+    let concerns_file =
+        |event: &notify::Event| !event.kind.is_access() && event.paths.iter().any(is_file);
+
+    // A rename within the directory is reported with both paths: [from, to].
+    let renamed_to = |event: &notify::Event| match (&event.kind, event.paths.as_slice()) {
+        (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) if is_file(from) => {
+            Some(to.clone())
+        }
+        _ => None,
+    };
+
+    while let Some(event) = events.next().await {
+        if !concerns_file(&event) {
+            continue;
+        }
+
+        let mut destination = renamed_to(&event);
+
+        // swallow the rest of the burst, remembering where the file went if it was moved;
+        // other files of the directory don't hold it up
+        let started = Instant::now();
+        let mut deadline = started + DEBOUNCE;
+
+        while let Ok(Some(event)) = timeout_at(deadline, events.next()).await {
+            if concerns_file(&event) {
+                destination = renamed_to(&event).or(destination);
+                deadline = (Instant::now() + DEBOUNCE).min(started + MAX_DEBOUNCE);
+            }
+        }
+
+        // Only trust what is on disk now: editors often delete or move the file away
+        // and then write a new one in its place.
+        let change = match FileStamp::of(&path).await {
+            Some(stamp) => Change::Modified(stamp),
+            None => match destination {
+                Some(destination) => Change::Renamed(destination),
+                None => Change::Deleted,
+            },
+        };
+
+        if output.send(change).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Watch everything under `root` and report once a burst of changes has settled.
+/// What changed is left to the caller, who reads the folder again.
+pub fn watch_folder(root: PathBuf) -> Subscription<()> {
+    Subscription::run_with_id(
+        (root.clone(), "folder"),
+        stream::channel(10, move |output| watch_tree(root, output)),
+    )
+}
+
+async fn watch_tree(root: PathBuf, mut output: mpsc::Sender<()>) {
+    let (sender, mut events) = mpsc::unbounded();
+
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && !event.kind.is_access()
+        {
+            let _ = sender.unbounded_send(event);
+        }
+    });
+
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            eprintln!("Could not watch {}: {}", root.display(), error);
+            return;
+        }
+    };
+
+    if let Err(error) = watcher.watch(&root, RecursiveMode::Recursive) {
+        eprintln!("Could not watch {}: {}", root.display(), error);
+        return;
+    }
+
+    while events.next().await.is_some() {
+        // swallow the rest of the burst
+        let started = Instant::now();
+        let mut deadline = started + DEBOUNCE;
+
+        while let Ok(Some(_)) = timeout_at(deadline, events.next()).await {
+            deadline = (Instant::now() + DEBOUNCE).min(started + MAX_DEBOUNCE);
+        }
+
+        if output.send(()).await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::montagne_assets::is_image;
+use crate::montagne_file_types::FileTypesConfig;
+
+/// Folders deeper than this aren't shown, a safeguard against huge trees.
+const MAX_DEPTH: usize = 16;
+
+/// A folder of notes opened with "Open folder", shown as a tree in the sidebar.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub nodes: Vec<Node>,
+    /// Folders showing their contents.
+    pub expanded: HashSet<PathBuf>,
+    /// Entry the sidebar actions apply to.
+    pub selected: Option<PathBuf>,
+}
+
+/// A file or folder of the workspace.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub path: PathBuf,
+    pub name: String,
+    /// What a folder holds, `None` for a file.
+    pub children: Option<Vec<Node>>,
+}
+
+/// What the user is asked about before changing the workspace on disk.
+#[derive(Debug, Clone)]
+pub enum Prompt {
+    /// Name of a new file in the folder.
+    NewFile(PathBuf),
+    /// Name of a new folder in the folder.
+    NewFolder(PathBuf),
+    /// New name of the entry.
+    Rename(PathBuf),
+    /// Confirm deleting the entry.
+    Delete(PathBuf),
+}
+
+/// Something we changed in the workspace on disk, open documents may have to follow.
+#[derive(Debug, Clone)]
+pub enum TreeChange {
+    Created(PathBuf),
+    Moved { from: PathBuf, to: PathBuf },
+    Deleted(PathBuf),
+}
+
+impl Workspace {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            nodes: Vec::new(),
+            expanded: HashSet::new(),
+            selected: None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.root.display().to_string())
+    }
+
+    pub fn find(&self, path: &Path) -> Option<&Node> {
+        let mut nodes = &self.nodes;
+
+        loop {
+            let node = nodes.iter().find(|node| path.starts_with(&node.path))?;
+
+            if node.path == path {
+                return Some(node);
+            }
+            nodes = node.children.as_ref()?;
+        }
+    }
+
+    pub fn is_folder(&self, path: &Path) -> bool {
+        path == self.root || self.find(path).is_some_and(|node| node.children.is_some())
+    }
+
+    /// Where new entries go: the selected folder, the folder of the selected file, or the root.
+    pub fn target_folder(&self) -> PathBuf {
+        match &self.selected {
+            Some(selected) if self.is_folder(selected) => selected.clone(),
+            Some(selected) => selected
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| self.root.clone()),
+            None => self.root.clone(),
+        }
+    }
+
+    /// Show the folder's contents, or hide them when shown.
+    pub fn toggle(&mut self, folder: &Path) {
+        if !self.expanded.remove(folder) {
+            self.expanded.insert(folder.to_path_buf());
+        }
+    }
+
+    /// Keep what was expanded and selected where it went after a move.
+    pub fn follow_move(&mut self, from: &Path, to: &Path) {
+        self.expanded = self
+            .expanded
+            .drain()
+            .map(|folder| moved_path(&folder, from, to).unwrap_or(folder))
+            .collect();
+
+        if let Some(selected) = &self.selected {
+            self.selected = moved_path(selected, from, to).or(self.selected.take());
+        }
+    }
+}
+
+/// A name for a new or renamed entry, not a way to put it somewhere else.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\']) && name != "." && name != ".."
+}
+
+/// Where `path` is once `from` moved to `to`, if it was inside it (or it).
+pub fn moved_path(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    path.strip_prefix(from).ok().map(|rest| to.join(rest))
+}
+
+/// Notes and the images they link to are what a notes folder is about.
+fn is_shown(path: &Path, file_types: &FileTypesConfig) -> bool {
+    is_image(path) || file_types.is_markdown(path) || file_types.is_text(path)
+}
+
+/// Read the tree under `root`, folders first, each level sorted by name. Hidden entries are left
+/// out, and symlinked folders aren't followed so a loop can't hang us.
+pub async fn read_tree(root: PathBuf, file_types: FileTypesConfig) -> Result<Vec<Node>, String> {
+    read_folder(&root, &file_types, 0)
+        .await
+        .map_err(|error| format!("Could not read {}: {}", root.display(), error))
+}
+
+async fn read_folder(
+    folder: &Path,
+    file_types: &FileTypesConfig,
+    depth: usize,
+) -> io::Result<Vec<Node>> {
+    let mut entries = tokio::fs::read_dir(folder).await?;
+    let mut nodes = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type().await else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            let children = if depth < MAX_DEPTH {
+                // one unreadable folder shouldn't hide the rest of the tree
+                Box::pin(read_folder(&path, file_types, depth + 1))
+                    .await
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+
+            nodes.push(Node {
+                path,
+                name,
+                children: Some(children),
+            });
+        } else if is_shown(&path, file_types) {
+            nodes.push(Node {
+                path,
+                name,
+                children: None,
+            });
+        }
+    }
+
+    nodes.sort_by(|a, b| {
+        b.children
+            .is_some()
+            .cmp(&a.children.is_some())
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+
+    Ok(nodes)
+}
+
+pub async fn pick_folder() -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .set_title("Open a folder...")
+        .pick_folder()
+        .await
+        .map(|folder| folder.path().to_path_buf())
+}
+
+/// Pick the folder to move `path` into, starting from where it is.
+pub async fn pick_destination(path: PathBuf) -> Option<PathBuf> {
+    let mut dialog = rfd::AsyncFileDialog::new().set_title("Move to folder...");
+
+    if let Some(folder) = path.parent() {
+        dialog = dialog.set_directory(folder);
+    }
+
+    dialog
+        .pick_folder()
+        .await
+        .map(|folder| folder.path().to_path_buf())
+}
+
+pub async fn create_file(path: PathBuf) -> Result<TreeChange, String> {
+    tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .await
+        .map_err(|error| describe(&path, error))?;
+
+    Ok(TreeChange::Created(path))
+}
+
+pub async fn create_folder(path: PathBuf) -> Result<TreeChange, String> {
+    tokio::fs::create_dir(&path)
+        .await
+        .map_err(|error| describe(&path, error))?;
+
+    Ok(TreeChange::Created(path))
+}
+
+/// Rename or move a file or folder, never over something that exists.
+pub async fn move_entry(from: PathBuf, to: PathBuf) -> Result<TreeChange, String> {
+    if to.starts_with(&from) {
+        return Err(format!("Can't move {} into itself", from.display()));
+    }
+
+    if tokio::fs::try_exists(&to).await.unwrap_or(false) {
+        return Err(format!("{} already exists", to.display()));
+    }
+
+    tokio::fs::rename(&from, &to)
+        .await
+        .map_err(|error| describe(&from, error))?;
+
+    Ok(TreeChange::Moved { from, to })
+}
+
+pub async fn delete_entry(path: PathBuf) -> Result<TreeChange, String> {
+    let metadata = tokio::fs::symlink_metadata(&path)
+        .await
+        .map_err(|error| describe(&path, error))?;
+
+    if metadata.is_dir() {
+        tokio::fs::remove_dir_all(&path).await
+    } else {
+        tokio::fs::remove_file(&path).await
+    }
+    .map_err(|error| describe(&path, error))?;
+
+    Ok(TreeChange::Deleted(path))
+}
+
+fn describe(path: &Path, error: io::Error) -> String {
+    format!("{}: {}", path.display(), error)
+}